@@ -0,0 +1,10 @@
+
+extern crate memenhancer;
+
+fn main() {
+    let pdf_file = "screenshots/meme.pdf";
+    let bob_str = include_str!("meme.mem");
+    let pdf = memenhancer::to_pdf(bob_str, &memenhancer::RenderOptions::new().rest_text(true), &memenhancer::PdfOptions::new());
+    std::fs::write(pdf_file, pdf).unwrap();
+    println!("Saved to {}", pdf_file);
+}
@@ -0,0 +1,10 @@
+
+extern crate memenhancer;
+
+fn main() {
+    let png_file = "screenshots/sample.png";
+    let bob_str = include_str!("sample.mem");
+    let png = memenhancer::to_png(bob_str, &memenhancer::RenderOptions::new().rest_text(true));
+    std::fs::write(png_file, png).unwrap();
+    println!("Saved to {}", png_file);
+}
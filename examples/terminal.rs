@@ -0,0 +1,14 @@
+
+extern crate memenhancer;
+
+use memenhancer::{Graphics, TerminalOptions};
+
+/// shows the memes of the file in the argument, or of examples/meme.mem, in this terminal
+fn main() {
+    let bob_str = match std::env::args().nth(1){
+        Some(file) => std::fs::read_to_string(file).unwrap(),
+        None => include_str!("meme.mem").to_string(),
+    };
+    let options = TerminalOptions::new().graphics(Graphics::detect());
+    print!("{}", memenhancer::to_terminal(&bob_str, &options));
+}
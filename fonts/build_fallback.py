@@ -0,0 +1,512 @@
+#!/usr/bin/env python3
+"""Builds MemenhancerFallback.ttf, the glyphs of the examples that the other bundled fonts don't have.
+
+The glyphs are traced from the bitmaps of GNU Unifont, which is given as a .hex file:
+
+    python3 fonts/build_fallback.py unifont-13.0.06.hex
+
+The mathematical letters are drawn as the letters they are styled from,
+and the skin tone modifiers as a square, since Unifont covers the basic multilingual plane only.
+The emojis that are newer than the bundled Noto Emoji are drawn with the outline of the older emoji closest to them.
+"""
+
+import glob
+import os
+import struct
+import sys
+import unicodedata
+
+FONTS = os.path.dirname(os.path.abspath(__file__))
+EXAMPLES = os.path.join(FONTS, "..", "examples")
+NOTO_EMOJI = "NotoEmoji-Regular.ttf"
+BUNDLED = ["DejaVuSans.ttf", "NotoSansKannada-Regular.ttf", NOTO_EMOJI]
+OUTPUT = os.path.join(FONTS, "MemenhancerFallback.ttf")
+
+FAMILY = "Memenhancer Fallback"
+UNITS_PER_EM = 1024
+# the bitmaps are 16 pixels tall, 2 of which are below the baseline
+PIXEL = 64
+DESCENT = 2
+SKIN_TONES = range(0x1F3FB, 0x1F400)
+
+# the emojis of the examples that are newer than the bundled Noto Emoji, and the older emoji they are drawn as
+SUBSTITUTES = {
+    # weather
+    0x1F321: 0x1F4CF, 0x1F324: 0x26C5, 0x1F325: 0x26C5, 0x1F326: 0x2614, 0x1F327: 0x2614,
+    0x1F328: 0x26C4, 0x1F329: 0x26A1, 0x1F32A: 0x1F300, 0x1F32B: 0x1F301, 0x1F32C: 0x1F4A8,
+    # food and drink
+    0x1F32D: 0x1F354, 0x1F32E: 0x1F355, 0x1F32F: 0x1F35E, 0x1F336: 0x1F346, 0x1F37D: 0x1F374,
+    0x1F37E: 0x1F376, 0x1F37F: 0x1F35F, 0x1F942: 0x1F37B, 0x1F943: 0x1F378, 0x1F944: 0x1F374,
+    0x1F950: 0x1F35E, 0x1F951: 0x1F350, 0x1F952: 0x1F346, 0x1F953: 0x1F356, 0x1F954: 0x1F360,
+    0x1F955: 0x1F33D, 0x1F956: 0x1F35E, 0x1F957: 0x1F372, 0x1F958: 0x1F373, 0x1F959: 0x1F35E,
+    0x1F95A: 0x1F373, 0x1F95B: 0x1F37C, 0x1F95C: 0x1F330, 0x1F95D: 0x1F348, 0x1F95E: 0x1F370,
+    0x1F9C0: 0x1F370,
+    # medals, music and films
+    0x1F396: 0x1F3C6, 0x1F397: 0x1F380, 0x1F399: 0x1F3A4, 0x1F39A: 0x1F4F6, 0x1F39B: 0x1F4FB,
+    0x1F39E: 0x1F3AC, 0x1F39F: 0x1F3AB, 0x1F3C5: 0x1F3C6, 0x1F947: 0x1F3C6, 0x1F948: 0x1F3C6,
+    0x1F949: 0x1F3C6, 0x1F941: 0x1F3B5, 0x1F4F8: 0x1F4F7, 0x1F4FD: 0x1F3A5,
+    # sports
+    0x1F3CB: 0x1F4AA, 0x1F3CC: 0x26F3, 0x1F3CD: 0x1F6B2, 0x1F3CE: 0x1F697, 0x1F3CF: 0x26BE,
+    0x1F3D0: 0x26BD, 0x1F3D1: 0x26BD, 0x1F3D2: 0x1F3BF, 0x1F3D3: 0x1F3BE, 0x1F3F8: 0x1F3BE,
+    0x1F3F9: 0x1F3AF, 0x1F945: 0x26BD, 0x1F94A: 0x1F44A, 0x1F94B: 0x1F458,
+    # places and buildings
+    0x1F3D4: 0x1F5FB, 0x1F3D5: 0x26FA, 0x1F3D6: 0x1F334, 0x1F3D7: 0x1F6A7, 0x1F3D8: 0x1F3E0,
+    0x1F3D9: 0x1F306, 0x1F3DA: 0x1F3E0, 0x1F3DB: 0x1F3E6, 0x1F3DC: 0x1F335, 0x1F3DD: 0x1F334,
+    0x1F3DE: 0x1F304, 0x1F3DF: 0x1F3AA, 0x1F54B: 0x26EA, 0x1F54C: 0x26EA, 0x1F54D: 0x26EA,
+    0x1F6D0: 0x26EA, 0x1F5FA: 0x1F30D,
+    # flags and symbols
+    0x1F3F3: 0x1F6A9, 0x1F3F4: 0x1F3C1, 0x1F3F5: 0x1F33C, 0x1F3F7: 0x1F516, 0x1F549: 0x1F52F,
+    0x1F54E: 0x1F52F, 0x1F5A4: 0x1F499, 0x1F5E8: 0x1F4AC, 0x1F5EF: 0x1F4A2, 0x1F6D1: 0x26D4,
+    # animals
+    0x1F42A: 0x1F42B, 0x1F43F: 0x1F401, 0x1F54A: 0x1F426, 0x1F577: 0x1F41C, 0x1F980: 0x1F41A,
+    0x1F981: 0x1F42F, 0x1F982: 0x1F41C, 0x1F983: 0x1F414, 0x1F984: 0x1F434, 0x1F985: 0x1F426,
+    0x1F986: 0x1F424, 0x1F987: 0x1F426, 0x1F988: 0x1F42C, 0x1F989: 0x1F426, 0x1F98A: 0x1F43A,
+    0x1F98B: 0x1F41D, 0x1F98C: 0x1F410, 0x1F98D: 0x1F435, 0x1F98E: 0x1F40A, 0x1F98F: 0x1F418,
+    0x1F990: 0x1F364, 0x1F991: 0x1F419, 0x1F940: 0x1F339,
+    # objects
+    0x1F3FA: 0x1F376, 0x1F4FF: 0x1F517, 0x1F56F: 0x1F525, 0x1F570: 0x23F0, 0x1F573: 0x26AB,
+    0x1F576: 0x1F453, 0x1F578: 0x1F310, 0x1F579: 0x1F3AE, 0x1F587: 0x1F4CE, 0x1F58A: 0x270F,
+    0x1F58B: 0x2712, 0x1F58C: 0x1F3A8, 0x1F58D: 0x270F, 0x1F5A5: 0x1F4BB, 0x1F5A8: 0x1F4E0,
+    0x1F5B1: 0x1F4BB, 0x1F5B2: 0x1F3B1, 0x1F5BC: 0x1F304, 0x1F5C2: 0x1F4C2, 0x1F5C3: 0x1F4C7,
+    0x1F5C4: 0x1F4C1, 0x1F5D1: 0x1F6AE, 0x1F5D2: 0x1F4DD, 0x1F5D3: 0x1F4C5, 0x1F5DC: 0x1F529,
+    0x1F5DD: 0x1F511, 0x1F5DE: 0x1F4F0, 0x1F5E1: 0x1F52A, 0x1F5F3: 0x1F4EE, 0x1F6CB: 0x1F4BA,
+    0x1F6CC: 0x1F4A4, 0x1F6CD: 0x1F45C, 0x1F6CE: 0x1F514, 0x1F6CF: 0x1F3E8, 0x1F6D2: 0x1F3EA,
+    0x1F6E0: 0x1F527, 0x1F6E1: 0x1F530, 0x1F6E2: 0x26FD, 0x1F933: 0x1F4F1,
+    # transport
+    0x1F698: 0x1F697, 0x1F6E3: 0x1F6A5, 0x1F6E4: 0x1F683, 0x1F6E5: 0x1F6A4, 0x1F6E9: 0x2708,
+    0x1F6EB: 0x2708, 0x1F6EC: 0x2708, 0x1F6F0: 0x1F4E1, 0x1F6F3: 0x1F6A2, 0x1F6F4: 0x1F6B2,
+    0x1F6F5: 0x1F6B2, 0x1F6F6: 0x1F6A3,
+    # faces
+    0x1F641: 0x1F61E, 0x1F642: 0x1F60A, 0x1F644: 0x1F612, 0x1F910: 0x1F636,
+    0x1F911: 0x1F4B0, 0x1F912: 0x1F637, 0x1F913: 0x1F60E, 0x1F914: 0x1F614, 0x1F915: 0x1F637,
+    0x1F916: 0x1F47E, 0x1F917: 0x1F603, 0x1F920: 0x1F3A9, 0x1F921: 0x1F61C, 0x1F922: 0x1F616,
+    0x1F923: 0x1F602, 0x1F924: 0x1F60B, 0x1F925: 0x1F60F, 0x1F927: 0x1F637,
+    # hands
+    0x1F590: 0x270B, 0x1F595: 0x1F446, 0x1F596: 0x270B, 0x1F918: 0x270C, 0x1F919: 0x1F44B,
+    0x1F91A: 0x270B, 0x1F91B: 0x1F44A, 0x1F91C: 0x1F44A, 0x1F91D: 0x1F44F, 0x1F91E: 0x270C,
+    # people
+    0x1F441: 0x1F440, 0x1F574: 0x1F6B6, 0x1F575: 0x1F50D, 0x1F57A: 0x1F483, 0x1F5E3: 0x1F464,
+    0x1F926: 0x1F64D, 0x1F930: 0x1F469, 0x1F934: 0x1F451, 0x1F935: 0x1F468, 0x1F936: 0x1F385,
+    0x1F937: 0x1F481, 0x1F938: 0x1F3C3, 0x1F939: 0x1F3AA, 0x1F93A: 0x1F3C3, 0x1F93C: 0x1F46C,
+    0x1F93D: 0x1F3CA, 0x1F93E: 0x1F3C0,
+}
+
+
+def read_tables(data):
+    """the offset of each table of the font"""
+    count = struct.unpack(">H", data[4:6])[0]
+    tables = {}
+    for i in range(count):
+        tag, _, offset, length = struct.unpack(">4sIII", data[12 + 16 * i:28 + 16 * i])
+        tables[tag] = offset
+    return tables
+
+
+def read_cmap(path):
+    """the code points mapped to a glyph by the format 4 and format 12 subtables of the font"""
+    return set(read_glyph_ids(path))
+
+
+def read_glyph_ids(path):
+    """the glyph id of each code point in the format 4 and format 12 subtables of the font"""
+    data = open(path, "rb").read()
+    cmap = read_tables(data)[b"cmap"]
+    chars = {}
+    for i in range(struct.unpack(">H", data[cmap + 2:cmap + 4])[0]):
+        offset = cmap + struct.unpack(">I", data[cmap + 8 + 8 * i:cmap + 12 + 8 * i])[0]
+        fmt = struct.unpack(">H", data[offset:offset + 2])[0]
+        if fmt == 4:
+            segments = struct.unpack(">H", data[offset + 6:offset + 8])[0] // 2
+            ends = struct.unpack(">%dH" % segments, data[offset + 14:offset + 14 + 2 * segments])
+            starts = struct.unpack(">%dH" % segments, data[offset + 16 + 2 * segments:offset + 16 + 4 * segments])
+            deltas = struct.unpack(">%dH" % segments, data[offset + 16 + 4 * segments:offset + 16 + 6 * segments])
+            range_offsets = offset + 16 + 6 * segments
+            for i, (start, end, delta) in enumerate(zip(starts, ends, deltas)):
+                range_offset = struct.unpack(">H", data[range_offsets + 2 * i:range_offsets + 2 * i + 2])[0]
+                for c in range(start, end + 1):
+                    if c == 0xFFFF:
+                        continue
+                    if range_offset == 0:
+                        gid = (c + delta) & 0xFFFF
+                    else:
+                        at = range_offsets + 2 * i + range_offset + 2 * (c - start)
+                        gid = struct.unpack(">H", data[at:at + 2])[0]
+                        gid = (gid + delta) & 0xFFFF if gid else 0
+                    if gid:
+                        chars[c] = gid
+        elif fmt == 12:
+            groups = struct.unpack(">I", data[offset + 12:offset + 16])[0]
+            for g in range(groups):
+                start, end, gid = struct.unpack(">III", data[offset + 16 + 12 * g:offset + 28 + 12 * g])
+                chars.update((c, gid + c - start) for c in range(start, end + 1) if gid + c - start)
+    return chars
+
+
+def read_outlines(path):
+    """the advance and the contours of each glyph of the font by its glyph id, scaled to the units per em of the output.
+    The contours are lists of points with a flag for the points on the curve, the composite glyphs are flattened"""
+    data = open(path, "rb").read()
+    tables = read_tables(data)
+    head = tables[b"head"]
+    units = struct.unpack(">H", data[head + 18:head + 20])[0]
+    long_loca = struct.unpack(">h", data[head + 50:head + 52])[0]
+    glyph_count = struct.unpack(">H", data[tables[b"maxp"] + 4:tables[b"maxp"] + 6])[0]
+    metrics = struct.unpack(">H", data[tables[b"hhea"] + 34:tables[b"hhea"] + 36])[0]
+    if long_loca:
+        loca = struct.unpack(">%dI" % (glyph_count + 1), data[tables[b"loca"]:tables[b"loca"] + 4 * (glyph_count + 1)])
+    else:
+        loca = [2 * o for o in struct.unpack(">%dH" % (glyph_count + 1), data[tables[b"loca"]:tables[b"loca"] + 2 * (glyph_count + 1)])]
+    glyf = tables[b"glyf"]
+    scale = UNITS_PER_EM / units
+
+    def contours(gid):
+        start, end = glyf + loca[gid], glyf + loca[gid + 1]
+        if start == end:
+            return []
+        count = struct.unpack(">h", data[start:start + 2])[0]
+        at = start + 10
+        if count < 0:
+            found = []
+            more = True
+            while more:
+                flags, component = struct.unpack(">HH", data[at:at + 4])
+                at += 4
+                if flags & 1:
+                    dx, dy = struct.unpack(">hh", data[at:at + 4])
+                    at += 4
+                else:
+                    dx, dy = struct.unpack(">bb", data[at:at + 2])
+                    at += 2
+                xx, xy, yx, yy = 1.0, 0.0, 0.0, 1.0
+                if flags & 0x8:
+                    xx = yy = struct.unpack(">h", data[at:at + 2])[0] / 16384
+                    at += 2
+                elif flags & 0x40:
+                    xx, yy = (v / 16384 for v in struct.unpack(">hh", data[at:at + 4]))
+                    at += 4
+                elif flags & 0x80:
+                    xx, xy, yx, yy = (v / 16384 for v in struct.unpack(">hhhh", data[at:at + 8]))
+                    at += 8
+                for contour in contours(component):
+                    found.append([(x * xx + y * yx + dx, x * xy + y * yy + dy, on) for x, y, on in contour])
+                more = flags & 0x20
+            return found
+        ends = struct.unpack(">%dH" % count, data[at:at + 2 * count])
+        at += 2 * count
+        at += 2 + struct.unpack(">H", data[at:at + 2])[0]
+        total = ends[-1] + 1 if ends else 0
+        flags = []
+        while len(flags) < total:
+            flag = data[at]
+            at += 1
+            repeat = 1
+            if flag & 8:
+                repeat += data[at]
+                at += 1
+            flags.extend([flag] * repeat)
+        coordinates = []
+        for short, same in ((2, 16), (4, 32)):
+            value = 0
+            values = []
+            for flag in flags:
+                if flag & short:
+                    delta = data[at]
+                    at += 1
+                    value += delta if flag & same else -delta
+                elif not flag & same:
+                    value += struct.unpack(">h", data[at:at + 2])[0]
+                    at += 2
+                values.append(value)
+            coordinates.append(values)
+        points = [(x, y, bool(flag & 1)) for x, y, flag in zip(coordinates[0], coordinates[1], flags)]
+        return [points[begin:end + 1] for begin, end in zip((0,) + tuple(e + 1 for e in ends), ends)]
+
+    def outline(gid):
+        at = tables[b"hmtx"] + 4 * min(gid, metrics - 1)
+        advance = struct.unpack(">H", data[at:at + 2])[0]
+        scaled = [[(round(x * scale), round(y * scale), on) for x, y, on in contour] for contour in contours(gid)]
+        return round(advance * scale), scaled
+
+    return outline
+
+
+def read_unifont(path):
+    """the rows of pixels of each code point, as a list of 16 rows of booleans"""
+    glyphs = {}
+    for line in open(path):
+        code, bits = line.strip().split(":")
+        width = len(bits) // 4
+        rows = []
+        for r in range(16):
+            row = int(bits[r * width // 4:(r + 1) * width // 4], 16)
+            rows.append([bool(row >> (width - 1 - x) & 1) for x in range(width)])
+        glyphs[int(code, 16)] = rows
+    return glyphs
+
+
+def is_invisible(c):
+    category = unicodedata.category(chr(c))
+    return category in ("Cc", "Zs", "Zl", "Zp") or 0x200B <= c <= 0x200F or 0xFE00 <= c <= 0xFE0F
+
+
+def clusters(text):
+    """the characters followed by the marks and the joiners combined with them"""
+    found = []
+    for c in text:
+        joined = found and found[-1][-1] == "\u200d"
+        if found and (unicodedata.category(c).startswith("M") or joined or is_invisible(ord(c)) and not c.isspace()):
+            found[-1] += c
+        else:
+            found.append(c)
+    return found
+
+
+def trace(rows):
+    """the contours around the pixels, clockwise around the ink and counter clockwise around the holes,
+    in pixels with y going up from the baseline"""
+    edges = {}
+    height = len(rows)
+    for r, row in enumerate(rows):
+        y = height - DESCENT - r - 1
+        for x, ink in enumerate(row):
+            if not ink:
+                continue
+            for start, end in [((x, y), (x, y + 1)), ((x, y + 1), (x + 1, y + 1)),
+                               ((x + 1, y + 1), (x + 1, y)), ((x + 1, y), (x, y))]:
+                # the edges shared by 2 pixels cancel out
+                if (end, start) in edges:
+                    del edges[(end, start)]
+                else:
+                    edges[(start, end)] = True
+    following = {}
+    for start, end in edges:
+        following.setdefault(start, []).append(end)
+    contours = []
+    while following:
+        start = next(iter(following))
+        contour = [start]
+        point = start
+        while True:
+            ends = following[point]
+            end = ends.pop()
+            if not ends:
+                del following[point]
+            if end == start:
+                break
+            contour.append(end)
+            point = end
+        contours.append(simplify(contour))
+    return contours
+
+
+def simplify(contour):
+    """the corners of the contour, without the points on a straight line"""
+    corners = []
+    for i, point in enumerate(contour):
+        before = contour[i - 1]
+        after = contour[(i + 1) % len(contour)]
+        if (point[0] - before[0]) * (after[1] - point[1]) != (point[1] - before[1]) * (after[0] - point[0]):
+            corners.append(point)
+    return corners
+
+
+def in_units(contours, shift):
+    """the contours in pixels as points on the curve in the units of the font, moved by the shift in pixels"""
+    return [[((x + shift) * PIXEL, y * PIXEL, True) for x, y in contour] for contour in contours]
+
+
+def glyf_entry(contours):
+    """a simple glyph from the contours in units, whose points are flagged when they are on the curve"""
+    if not contours:
+        return b""
+    points = [point for contour in contours for point in contour]
+    xs = [x for x, _, _ in points]
+    ys = [y for _, y, _ in points]
+    data = struct.pack(">hhhhh", len(contours), min(xs), min(ys), max(xs), max(ys))
+    end = -1
+    for contour in contours:
+        end += len(contour)
+        data += struct.pack(">H", end)
+    data += struct.pack(">H", 0)
+    data += bytes([1 if on else 0 for _, _, on in points])
+    previous = 0
+    for x, _, _ in points:
+        data += struct.pack(">h", x - previous)
+        previous = x
+    previous = 0
+    for _, y, _ in points:
+        data += struct.pack(">h", y - previous)
+        previous = y
+    return data
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def name_table():
+    names = [
+        (0, "Glyphs from GNU Unifont and Noto Emoji, SIL Open Font License 1.1"),
+        (1, FAMILY),
+        (2, "Regular"),
+        (3, FAMILY + " Regular"),
+        (4, FAMILY + " Regular"),
+        (5, "Version 1.0"),
+        (6, FAMILY.replace(" ", "") + "-Regular"),
+    ]
+    records = b""
+    strings = b""
+    for name_id, text in names:
+        encoded = text.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    return struct.pack(">HHH", 0, len(names), 6 + 12 * len(names)) + records + strings
+
+
+def cmap_table(mapping):
+    """a format 4 subtable for the basic multilingual plane and a format 12 subtable for all of the code points"""
+    codes = sorted(mapping)
+    bmp = [c for c in codes if c < 0xFFFF]
+    segments = []
+    for c in bmp:
+        if segments and segments[-1][1] == c - 1 and mapping[c] - c == mapping[segments[-1][0]] - segments[-1][0]:
+            segments[-1][1] = c
+        else:
+            segments.append([c, c])
+    segments.append([0xFFFF, 0xFFFF])
+    count = len(segments)
+    search = 2 ** (count.bit_length() - 1)
+    format4 = struct.pack(">HHHH", count * 2, search * 2, search.bit_length() - 1, count * 2 - search * 2)
+    format4 += b"".join(struct.pack(">H", end) for _, end in segments) + struct.pack(">H", 0)
+    format4 += b"".join(struct.pack(">H", start) for start, _ in segments)
+    format4 += b"".join(struct.pack(">H", (mapping.get(start, 0) - start) & 0xFFFF if start != 0xFFFF else 1)
+                        for start, _ in segments)
+    format4 += struct.pack(">%dH" % count, *([0] * count))
+    format4 = struct.pack(">HHH", 4, 6 + len(format4), 0) + format4
+
+    groups = []
+    for c in codes:
+        if groups and groups[-1][1] == c - 1 and groups[-1][2] + c - groups[-1][0] == mapping[c]:
+            groups[-1][1] = c
+        else:
+            groups.append([c, c, mapping[c]])
+    format12 = struct.pack(">HHIII", 12, 0, 16 + 12 * len(groups), 0, len(groups))
+    format12 += b"".join(struct.pack(">III", *group) for group in groups)
+
+    header = struct.pack(">HH", 0, 2)
+    header += struct.pack(">HHI", 3, 1, 4 + 16)
+    header += struct.pack(">HHI", 3, 10, 4 + 16 + len(format4))
+    return header + format4 + format12
+
+
+def build(glyphs):
+    """the font of the glyphs, which are tuples of the code point, the advance and the contours in units"""
+    glyf = b""
+    loca = [0]
+    hmtx = b""
+    boxes = []
+    mapping = {}
+    for gid, (code, advance, contours) in enumerate(glyphs):
+        entry = glyf_entry(contours)
+        if entry:
+            boxes.append(struct.unpack(">hhhh", entry[2:10]))
+        entry += b"\0" * (-len(entry) % 4)
+        glyf += entry
+        loca.append(len(glyf))
+        left = struct.unpack(">h", entry[2:4])[0] if entry else 0
+        hmtx += struct.pack(">Hh", advance, left)
+        if code is not None:
+            mapping[code] = gid
+    x_min = min(b[0] for b in boxes)
+    y_min = min(b[1] for b in boxes)
+    x_max = max(b[2] for b in boxes)
+    y_max = max(b[3] for b in boxes)
+    ascent = (16 - DESCENT) * PIXEL
+    descent = -DESCENT * PIXEL
+    max_points = max(sum(len(c) for c in contours) for _, _, contours in glyphs)
+    max_contours = max(len(contours) for _, _, contours in glyphs)
+
+    tables = {
+        b"head": struct.pack(">IIIIHHqqhhhhHHhhh", 0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0x000B, UNITS_PER_EM,
+                             0, 0, x_min, y_min, x_max, y_max, 0, 8, 2, 1, 0),
+        b"hhea": struct.pack(">IhhhHhhhhhhhhhhhH", 0x00010000, ascent, descent, 0, 16 * PIXEL,
+                             min(0, x_min), min(0, x_min), x_max, 1, 0, 0, 0, 0, 0, 0, 0, len(glyphs)),
+        b"maxp": struct.pack(">IHHHHHHHHHHHHHH", 0x00010000, len(glyphs), max_points, max_contours,
+                             0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0),
+        b"OS/2": struct.pack(">HhHHHhhhhhhhhhhh10sIIII4sHHHhhhHHIIhhHHH",
+                             4, 8 * PIXEL, 400, 5, 0, 650, 700, 0, 140, 650, 700, 0, 480, 50, 260, 0,
+                             b"\0" * 10, 0, 0, 0, 0, b"MEME", 0x40,
+                             min(c for c in mapping), min(max(c for c in mapping), 0xFFFF),
+                             ascent, descent, 0, ascent, -descent, 1, 0, 10 * PIXEL, 12 * PIXEL, 0, 32, 0),
+        b"hmtx": hmtx,
+        b"cmap": cmap_table(mapping),
+        b"loca": struct.pack(">%dI" % len(loca), *loca),
+        b"glyf": glyf,
+        b"name": name_table(),
+        b"post": struct.pack(">IIhhIIIII", 0x00030000, 0, -2 * PIXEL, PIXEL, 0, 0, 0, 0, 0),
+    }
+    tags = sorted(tables)
+    search = 2 ** (len(tags).bit_length() - 1)
+    font = struct.pack(">IHHHH", 0x00010000, len(tags), search * 16, search.bit_length() - 1, len(tags) * 16 - search * 16)
+    offset = 12 + 16 * len(tags)
+    body = b""
+    for tag in tags:
+        data = tables[tag]
+        if tag == b"head":
+            head = offset + len(body)
+        font += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+    font += body
+    adjustment = (0xB1B0AFBA - checksum(font)) & 0xFFFFFFFF
+    return font[:head + 8] + struct.pack(">I", adjustment) + font[head + 12:]
+
+
+def main():
+    unifont = read_unifont(sys.argv[1])
+    emoji_ids = read_glyph_ids(os.path.join(FONTS, NOTO_EMOJI))
+    emoji_outline = read_outlines(os.path.join(FONTS, NOTO_EMOJI))
+    covered = set()
+    for name in BUNDLED:
+        covered |= read_cmap(os.path.join(FONTS, name))
+    needed = set()
+    for path in glob.glob(os.path.join(EXAMPLES, "*.mem")):
+        for cluster in clusters(open(path, encoding="utf-8").read()):
+            chars = [c for c in map(ord, cluster) if not is_invisible(c)]
+            # the whole cluster is taken from this font when any of it is missing from the others,
+            # so the marks are placed on the character they are combined with
+            if any(c not in covered for c in chars):
+                needed.update(chars)
+    missing = sorted(needed)
+
+    # the notdef glyph is an empty box
+    box = [[(0, -DESCENT), (0, 14 - DESCENT), (7, 14 - DESCENT), (7, -DESCENT)],
+           [(1, 1 - DESCENT), (6, 1 - DESCENT), (6, 13 - DESCENT), (1, 13 - DESCENT)]]
+    glyphs = [(None, 8 * PIXEL, in_units(box, 0))]
+    for c in missing:
+        if c in SUBSTITUTES:
+            advance, contours = emoji_outline(emoji_ids[SUBSTITUTES[c]])
+            glyphs.append((c, advance, contours))
+            continue
+        letter = unicodedata.normalize("NFKC", chr(c))
+        if c in unifont:
+            rows = unifont[c]
+        elif len(letter) == 1 and ord(letter) in unifont:
+            rows = unifont[ord(letter)]
+        elif c in SKIN_TONES:
+            rows = [[3 <= x <= 12 and 2 <= r <= 11 for x in range(16)] for r in range(16)]
+        else:
+            print("no glyph for U+%04X %s" % (c, unicodedata.name(chr(c), "")), file=sys.stderr)
+            continue
+        width = len(rows[0])
+        if unicodedata.combining(chr(c)) or unicodedata.category(chr(c)) in ("Mn", "Me"):
+            # the marks are drawn over the character before them
+            glyphs.append((c, 0, in_units(trace(rows), -width)))
+        else:
+            glyphs.append((c, width * PIXEL, in_units(trace(rows), 0)))
+    open(OUTPUT, "wb").write(build(glyphs))
+    print("%d glyphs written to %s" % (len(glyphs), OUTPUT))
+
+
+if __name__ == "__main__":
+    main()
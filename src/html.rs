@@ -0,0 +1,174 @@
+//! A single html document of the text with the memes drawn over it.
+//!
+//! The text is kept in a `pre`, so it can still be selected and copied,
+//! and the svg of the memes is laid over it on the same grid of cells.
+//! The svg is sized in `ch` and in lines, so it follows the width of the monospace font of the browser.
+
+use {parse_memes, to_svg_with_view_box, Content, Element, Overlap, RenderOptions};
+
+/// Options on the html document,
+/// built by chaining the setters starting from `HtmlOptions::new()`
+#[derive(Clone,Debug)]
+pub struct HtmlOptions {
+    title: String,
+    render: RenderOptions,
+}
+
+impl HtmlOptions {
+
+    pub fn new() -> HtmlOptions {
+        HtmlOptions::default()
+    }
+
+    /// the title of the document
+    pub fn title(mut self, title: &str) -> HtmlOptions {
+        self.title = title.to_string();
+        self
+    }
+
+    /// the options on how the memes are drawn,
+    /// the rest of the text is always left to the `pre` and the lines are not spaced apart
+    pub fn render_options(mut self, render: RenderOptions) -> HtmlOptions {
+        self.render = render;
+        self
+    }
+}
+
+impl Default for HtmlOptions {
+    fn default() -> HtmlOptions {
+        HtmlOptions{
+            title: "Memenhancer".to_string(),
+            render: RenderOptions::default(),
+        }
+    }
+}
+
+/// return an html document of the text with the memes drawn over it,
+/// the document has no external stylesheets, scripts or images
+pub fn to_html(s: &str, html_options: &HtmlOptions) -> String {
+    let options = &html_options.render;
+    let mut overlay_options = options.clone()
+        .rest_text(false)
+        .preserve_aspect_ratio("none");
+    overlay_options.theme.background = None;
+    // the lines of the pre are evenly spaced
+    if overlay_options.overlap == Overlap::Spacing{
+        overlay_options.overlap = Overlap::Clamp;
+    }
+    let (mut svg, view_box) = to_svg_with_view_box(s, &overlay_options);
+    svg.assign("style", "display: block; width: 100%; height: 100%;");
+    if !options.accessible{
+        // the text is read from the pre
+        svg.assign("aria-hidden", "true");
+    }
+    // the cell at the first column of the first line is at the padding of the svg
+    let (offsetx, offsety) = options.offset();
+    let overlay = Element::new("div")
+        .set("class", "overlay")
+        .set("style", format!("left: {}ch; top: {}px; width: {}ch; height: {}px;",
+            (view_box.left - offsetx) / options.text_width,
+            view_box.top - offsety,
+            (view_box.right - view_box.left) / options.text_width,
+            view_box.bottom - view_box.top))
+        .child(Content::Element(svg));
+
+    let document = Element::new("html")
+        .child(Content::Element(Element::new("head")
+            .child(Content::Element(Element::new("meta").set("charset", "utf-8")))
+            .child(Content::Element(Element::new("title").text(&html_options.title)))
+            .child(Content::Element(Element::new("style").text(&get_page_styles(options))))))
+        .child(Content::Element(Element::new("body")
+            .child(Content::Element(Element::new("div")
+                .set("class", "memenhancer")
+                .child(Content::Element(get_pre(s, options)))
+                .child(Content::Element(overlay))))));
+    format!("<!DOCTYPE html>\n{}\n", document)
+}
+
+/// the text with each meme in a span, which is hidden under the drawing of the meme
+fn get_pre(s: &str, options: &RenderOptions) -> Element {
+    // the newline right after the opening tag is dropped by the browser, and not the first empty line
+    let mut pre = Element::new("pre").text("\n");
+    for line in s.lines(){
+        let body = parse_memes(line, &options.parse);
+        let mut position = 0;
+        for meme in &body.memes{
+            pre = pre.text(&line[position..meme.span.start])
+                .child(Content::Element(Element::new("span")
+                    .set("class", "meme")
+                    .text(&line[meme.span.clone()])));
+            position = meme.span.end;
+        }
+        pre = pre.text(&format!("{}\n", &line[position..]));
+    }
+    pre
+}
+
+/// the pre and the drawing share the font size and the line height of the cells
+fn get_page_styles(options: &RenderOptions) -> String {
+    let theme = &options.theme;
+    format!(r#"
+    body {{
+        margin: 0;
+        padding: 2em;
+    }}
+    .memenhancer {{
+        position: relative;
+        display: inline-block;
+        font-family: monospace;
+        font-size: {font_size}px;
+        line-height: {text_height}px;
+    }}
+    .memenhancer pre {{
+        margin: 0;
+        font: inherit;
+        color: {text_color};
+        background: {background};
+    }}
+    .memenhancer .meme {{
+        color: transparent;
+    }}
+    .memenhancer .overlay {{
+        position: absolute;
+        pointer-events: none;
+    }}
+    "#,
+        font_size = options.font_size,
+        text_height = options.text_height,
+        text_color = theme.text,
+        background = theme.background.as_ref().map_or("none", |background| background.as_str()),
+    )
+}
+
+#[test]
+fn test_html(){
+    let text = "\nmeme_happy => Happy face => ( ツ)\n<b>&</b> ¯\\_(ツ)_/¯\n";
+    let options = RenderOptions::new().overlap(Overlap::Spacing);
+    let html = to_html(text, &HtmlOptions::new().title("Memes").render_options(options));
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    let document = ::roxmltree::Document::parse_with_options(&html, ::roxmltree::ParsingOptions{allow_dtd: true, ..Default::default()}).unwrap();
+    let find = |name: &str| document.descendants().find(|node| node.has_tag_name(name)).unwrap();
+    assert_eq!(Some("Memes"), find("title").text());
+
+    // the pre holds the whole text, with the memes in spans
+    let pre: String = find("pre").descendants().filter(|node| node.is_text()).filter_map(|node| node.text()).collect();
+    assert_eq!(format!("\n{}", text), pre);
+    let memes: Vec<&str> = document.descendants()
+        .filter(|node| node.has_tag_name("span"))
+        .filter_map(|node| node.text())
+        .collect();
+    assert_eq!(vec!["( ツ)", "¯\\_(ツ)_/¯"], memes);
+
+    // the svg draws the memes only, without a background
+    let svg = find("svg");
+    assert_eq!(Some("none"), svg.attribute("preserveAspectRatio"));
+    assert_eq!(2, svg.descendants().filter(|node| node.has_tag_name("g")).count());
+    assert!(!svg.descendants().any(|node| node.attribute("class") == Some("background") || node.attribute("class") == Some("rest")));
+    // the lines are not spaced apart, the second meme is on the third line
+    let group = svg.descendants().filter(|node| node.has_tag_name("g")).nth(1).unwrap();
+    let face = group.descendants().find(|node| node.attribute("class") == Some("face")).unwrap();
+    assert_eq!(Some("44"), face.attribute("y"));
+
+    // nothing is loaded from the network
+    assert!(!document.descendants().any(|node| node.has_attribute("src") || node.has_attribute("href")));
+}
@@ -1,40 +1,567 @@
 #![deny(warnings)]
 extern crate unicode_width;
-extern crate svg;
+extern crate unicode_segmentation;
+#[cfg(feature = "png")]
+extern crate resvg;
+#[cfg(feature = "pdf")]
+extern crate pdf_writer;
+#[cfg(feature = "pdf")]
+extern crate subsetter;
+#[cfg(feature = "pdf")]
+extern crate rustybuzz;
+#[cfg(feature = "pdf")]
+extern crate miniz_oxide;
+#[cfg(feature = "pdf")]
+extern crate svgtypes;
+#[cfg(feature = "terminal")]
+extern crate base64;
+#[cfg(feature = "terminal")]
+extern crate icy_sixel;
+#[cfg(test)]
+extern crate roxmltree;
 
 
-use unicode_width::UnicodeWidthStr;
 use unicode_width::UnicodeWidthChar;
-use svg::node::element::Circle as SvgCircle;
-use svg::node::element::Text as SvgText;
-use svg::Node;
-use svg::node::element::SVG;
-use svg::node::element::Style;
-use svg::node::Text as TextNode;
-
-
-struct Settings {
+use unicode_segmentation::UnicodeSegmentation;
+use std::ops::Range;
+use std::sync::Arc;
+use std::fmt;
+
+mod html;
+pub use html::{to_html, HtmlOptions};
+#[cfg(feature = "pdf")]
+mod pdf;
+#[cfg(feature = "pdf")]
+pub use pdf::{to_pdf, PdfOptions, PaperSize};
+#[cfg(feature = "terminal")]
+mod terminal;
+#[cfg(feature = "terminal")]
+pub use terminal::{to_terminal, line_to_terminal, TerminalOptions, Graphics};
+
+
+/// Options on how the memes are drawn in the svg,
+/// built by chaining the setters starting from `RenderOptions::new()`
+#[derive(Clone,Debug)]
+pub struct RenderOptions {
     text_width: f32,
     text_height: f32,
+    font_family: String,
+    font_size: f32,
+    padding: (f32, f32),
+    baseline: f32,
+    stroke_width: f32,
+    head_stroke_width: f32,
+    theme: Theme,
+    head_shape: HeadShape,
+    overlap: Overlap,
+    sizing: Sizing,
+    preserve_aspect_ratio: Option<String>,
+    rest_text: bool,
+    grid_fit: GridFit,
+    accessible: bool,
+    css: Option<String>,
+    extra_css: String,
+    colorizer: Option<Arc<dyn Colorizer + Send + Sync>>,
+    parse: ParseOptions,
 }
 
-impl Settings{
-    
+impl RenderOptions{
+
+    pub fn new() -> RenderOptions {
+        RenderOptions::default()
+    }
+
+    /// the width and height in pixels of 1 column of text
+    pub fn cell_size(mut self, text_width: f32, text_height: f32) -> RenderOptions {
+        self.text_width = text_width;
+        self.text_height = text_height;
+        self
+    }
+
+    pub fn font_family(mut self, font_family: &str) -> RenderOptions {
+        self.font_family = font_family.to_string();
+        self
+    }
+
+    pub fn font_size(mut self, font_size: f32) -> RenderOptions {
+        self.font_size = font_size;
+        self
+    }
+
+    /// the space in pixels around the drawing, the drawing is offset by it
+    pub fn padding(mut self, x: f32, y: f32) -> RenderOptions {
+        self.padding = (x, y);
+        self
+    }
+
+    /// the location of the text baseline as a ratio of the cell height from the top of the cell
+    pub fn baseline(mut self, ratio: f32) -> RenderOptions {
+        self.baseline = ratio;
+        self
+    }
+
+    /// the stroke width of the lines and the outline of the heads
+    pub fn stroke_width(mut self, stroke_width: f32) -> RenderOptions {
+        self.stroke_width = stroke_width;
+        self
+    }
+
+    /// the stroke width of the outline drawn around the head of a meme
+    pub fn head_stroke_width(mut self, head_stroke_width: f32) -> RenderOptions {
+        self.head_stroke_width = head_stroke_width;
+        self
+    }
+
+    /// the colors of the drawing
+    pub fn theme(mut self, theme: Theme) -> RenderOptions {
+        self.theme = theme;
+        self
+    }
+
+    /// the shape of the outline drawn around the head of a meme
+    pub fn head_shape(mut self, head_shape: HeadShape) -> RenderOptions {
+        self.head_shape = head_shape;
+        self
+    }
+
+    /// how the outline of a head that collides with the other lines is resolved
+    pub fn overlap(mut self, overlap: Overlap) -> RenderOptions {
+        self.overlap = overlap;
+        self
+    }
+
+    /// the unit of the width and height of the svg
+    pub fn sizing(mut self, sizing: Sizing) -> RenderOptions {
+        self.sizing = sizing;
+        self
+    }
+
+    /// how the drawing is scaled into the width and height of the svg,
+    /// such as `xMidYMid meet` or `none`
+    pub fn preserve_aspect_ratio(mut self, preserve_aspect_ratio: &str) -> RenderOptions {
+        self.preserve_aspect_ratio = Some(preserve_aspect_ratio.to_string());
+        self
+    }
+
+    /// also draw the text that is not a part of a meme, including the lines without any memes,
+    /// so the svg is a picture of the whole input
+    pub fn rest_text(mut self, rest_text: bool) -> RenderOptions {
+        self.rest_text = rest_text;
+        self
+    }
+
+    /// how the text is fitted to the grid of cells
+    pub fn grid_fit(mut self, grid_fit: GridFit) -> RenderOptions {
+        self.grid_fit = grid_fit;
+        self
+    }
+
+    /// describe the drawing to screen readers,
+    /// the svg is titled with the original text and each meme is an image labeled with its name
+    pub fn accessible(mut self, accessible: bool) -> RenderOptions {
+        self.accessible = accessible;
+        self
+    }
+
+    /// use this css in place of the stylesheet generated from the theme
+    pub fn replace_css(mut self, css: &str) -> RenderOptions {
+        self.css = Some(css.to_string());
+        self
+    }
+
+    /// add this css after the stylesheet, so its rules take precedence
+    pub fn append_css(mut self, css: &str) -> RenderOptions {
+        self.extra_css.push_str(css);
+        self.extra_css.push('\n');
+        self
+    }
+
+    /// color the heads depending on their face, such as the `Palette`
+    pub fn colorizer<C: Colorizer + Send + Sync + 'static>(mut self, colorizer: C) -> RenderOptions {
+        self.colorizer = Some(Arc::new(colorizer));
+        self
+    }
+
+    /// the options used in parsing the memes from the text
+    pub fn parse_options(mut self, parse: ParseOptions) -> RenderOptions {
+        self.parse = parse;
+        self
+    }
+
     fn offset(&self)->(f32, f32){
-        //(self.text_width * 1.0, self.text_height * 2.0)
-        (0.0, 0.0)
+        self.padding
     }
 }
 
-impl Default for Settings {
-    fn default() -> Settings {
-        Settings {
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
             text_width: 8.0,
             text_height: 16.0,
+            font_family: "arial".to_string(),
+            font_size: 14.0,
+            padding: (0.0, 0.0),
+            baseline: 3.0 / 4.0,
+            stroke_width: 2.0,
+            head_stroke_width: 1.0,
+            theme: Theme::default(),
+            head_shape: HeadShape::default(),
+            overlap: Overlap::default(),
+            sizing: Sizing::default(),
+            preserve_aspect_ratio: None,
+            rest_text: false,
+            grid_fit: GridFit::default(),
+            accessible: false,
+            css: None,
+            extra_css: String::new(),
+            colorizer: None,
+            parse: ParseOptions::default(),
+        }
+    }
+}
+
+/// The colors used in the drawing, any css color value can be used.
+///
+/// The parts of the drawing have these class names, which the css can target:
+/// `text.face` the face of a meme, `.donger` the outline of the head,
+/// `tspan.head` the brackets of the head, `text.arm` the arms on either side of the head,
+/// `text.rest` the text that is not a part of a meme and `rect.background` the background
+#[derive(Clone,Debug,PartialEq)]
+pub struct Theme {
+    /// the fill of the background, none for a transparent background
+    pub background: Option<String>,
+    /// the color of the text
+    pub text: String,
+    /// the color of the lines and the outline of the heads
+    pub stroke: String,
+    /// the fill of the heads
+    pub head_fill: String,
+}
+
+impl Theme {
+
+    /// black on white
+    pub fn light() -> Theme {
+        Theme{
+            background: Some("white".to_string()),
+            text: "black".to_string(),
+            stroke: "black".to_string(),
+            head_fill: "white".to_string(),
+        }
+    }
+
+    /// light gray on dark gray
+    pub fn dark() -> Theme {
+        Theme{
+            background: Some("#1e1e1e".to_string()),
+            text: "#e6e6e6".to_string(),
+            stroke: "#e6e6e6".to_string(),
+            head_fill: "#1e1e1e".to_string(),
+        }
+    }
+
+    /// white text and yellow outlines on black
+    pub fn high_contrast() -> Theme {
+        Theme{
+            background: Some("black".to_string()),
+            text: "white".to_string(),
+            stroke: "yellow".to_string(),
+            head_fill: "black".to_string(),
+        }
+    }
+
+    /// no background and unfilled heads, drawn in the color of the surrounding content
+    pub fn transparent() -> Theme {
+        Theme{
+            background: None,
+            text: "currentColor".to_string(),
+            stroke: "currentColor".to_string(),
+            head_fill: "none".to_string(),
         }
     }
 }
 
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::light()
+    }
+}
+
+/// The fill and stroke of a head and the color of its face, none keeps the color of the theme
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct HeadStyle {
+    pub fill: Option<String>,
+    pub stroke: Option<String>,
+    pub face: Option<String>,
+}
+
+impl HeadStyle {
+
+    pub fn new(fill: &str, stroke: &str) -> HeadStyle {
+        HeadStyle{
+            fill: Some(fill.to_string()),
+            stroke: Some(stroke.to_string()),
+            face: None,
+        }
+    }
+
+    /// the color of the face, for a fill the text of the theme can't be seen on
+    pub fn face(mut self, color: &str) -> HeadStyle {
+        self.face = Some(color.to_string());
+        self
+    }
+
+    /// the inline css, which takes precedence over the stylesheet
+    fn to_css(&self) -> String {
+        let mut css = String::new();
+        if let Some(ref fill) = self.fill{
+            css.push_str(&format!("fill: {};", fill));
+        }
+        if let Some(ref stroke) = self.stroke{
+            css.push_str(&format!("stroke: {};", stroke));
+        }
+        css
+    }
+}
+
+/// Picks the style of a head from its face
+pub trait Colorizer: fmt::Debug {
+    /// the style of the head with this face, none to use the theme
+    fn colorize(&self, face: &str) -> Option<HeadStyle>;
+}
+
+/// A colorizer that matches the characters in the face against its rules
+#[derive(Clone,Debug,PartialEq)]
+pub struct Palette {
+    rules: Vec<(String, HeadStyle)>,
+}
+
+impl Palette {
+
+    /// a palette without any rules
+    pub fn new() -> Palette {
+        Palette{rules: vec![]}
+    }
+
+    /// the faces that contain any of the characters are drawn in this style,
+    /// a rule takes precedence over the rules that are added before it
+    pub fn rule(mut self, chars: &str, style: HeadStyle) -> Palette {
+        self.rules.push((chars.to_string(), style));
+        self
+    }
+}
+
+impl Default for Palette {
+    /// angry faces are red, crying faces are blue and the faces with shades are black
+    fn default() -> Palette {
+        Palette::new()
+            .rule("益ಠ", HeadStyle::new("#ffd5d5", "red"))
+            .rule("ಥ", HeadStyle::new("#d5e5ff", "blue"))
+            .rule("■", HeadStyle::new("black", "black").face("white"))
+    }
+}
+
+impl Colorizer for Palette {
+    fn colorize(&self, face: &str) -> Option<HeadStyle> {
+        self.rules.iter().rev()
+            .find(|(chars, _)| face.chars().any(|c| chars.contains(c)))
+            .map(|(_, style)| style.clone())
+    }
+}
+
+/// The shape of the outline drawn around the head of a meme
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum HeadShape {
+    /// a circle as wide as the head, which is taller than a line for wide faces
+    #[default]
+    Circle,
+    /// an ellipse as wide as the head and as tall as a line
+    Ellipse,
+    /// a rectangle with rounded corners as wide as the head and as tall as a line
+    RoundedRect,
+    /// the shape that resembles the bracket pair,
+    /// a circle for ʕ ʔ, an ellipse for ( ) and a rounded rectangle for the other brackets such as ༼ ༽ and [ ]
+    Bracket,
+}
+
+impl HeadShape {
+
+    /// the shape to draw for the head enclosed in the bracket
+    fn infer(&self, bracket: Option<Bracket>) -> HeadShape {
+        match *self{
+            HeadShape::Bracket => match bracket.map(|b| b.open){
+                Some('ʕ') => HeadShape::Circle,
+                Some('(') | Some('（') | None => HeadShape::Ellipse,
+                Some(_) => HeadShape::RoundedRect,
+            },
+            shape => shape,
+        }
+    }
+}
+
+/// How the outline of a head that collides with the text or the heads of the other lines is resolved
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum Overlap {
+    /// the outline is drawn as it is
+    Allow,
+    /// the vertical radius is reduced to the room in between the colliding lines,
+    /// but not less than half of the line height
+    #[default]
+    Clamp,
+    /// the outline is reduced to an ellipse as tall as a line
+    Ellipse,
+    /// extra space is inserted in between the lines so the outline fits
+    Spacing,
+}
+
+/// The unit of the width and height of the svg, the viewBox is always in pixels
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum Sizing {
+    #[default]
+    Pixels,
+    /// relative to the font size, so the drawing scales along with the surrounding text
+    Em,
+}
+
+/// How the text is fitted to the grid of cells,
+/// so the memes line up with the surrounding ascii art drawn in a monospace font
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum GridFit {
+    /// the text flows in the font, the arms are anchored to the head
+    #[default]
+    Off,
+    /// every grapheme is positioned at the x location of its cell
+    Glyph,
+    /// every word is stretched with `textLength` to the width of its cells
+    TextLength,
+}
+
+/// A pair of open and close characters that encloses the face of a meme
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Bracket{
+    pub open: char,
+    pub close: char,
+}
+
+/// the bracket pairs that are recognized as the head of a meme
+pub const BRACKETS: [Bracket; 5] = [
+    Bracket{open: '(', close: ')'},
+    Bracket{open: '（', close: '）'},
+    Bracket{open: '༼', close: '༽'},
+    Bracket{open: '【', close: '】'},
+    Bracket{open: 'ʕ', close: 'ʔ'},
+];
+
+/// An element of the drawing, built as a typed tree
+/// so it can be inspected and modified before it is written out as svg
+#[derive(Clone,Debug,PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Content>,
+}
+
+/// The content of an element
+#[derive(Clone,Debug,PartialEq)]
+pub enum Content {
+    Element(Element),
+    /// the text, which is escaped when written out
+    Text(String),
+}
+
+impl Element {
+
+    pub fn new(name: &str) -> Element {
+        Element{
+            name: name.to_string(),
+            attributes: vec![],
+            children: vec![],
+        }
+    }
+
+    pub fn set<T: fmt::Display>(mut self, name: &str, value: T) -> Element {
+        self.assign(name, value);
+        self
+    }
+
+    /// set the attribute, replacing the value it already has
+    pub fn assign<T: fmt::Display>(&mut self, name: &str, value: T) {
+        let value = value.to_string();
+        match self.attributes.iter_mut().find(|(n, _)| n == name){
+            Some(attribute) => attribute.1 = value,
+            None => self.attributes.push((name.to_string(), value)),
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(mut self, content: Content) -> Element {
+        self.children.push(content);
+        self
+    }
+
+    pub fn text(self, text: &str) -> Element {
+        self.child(Content::Text(text.to_string()))
+    }
+
+    /// the elements in the tree under this element, in document order
+    pub fn descendants(&self) -> Vec<&Element> {
+        let mut elements = vec![];
+        for child in &self.children{
+            if let Content::Element(ref element) = *child{
+                elements.push(element);
+                elements.extend(element.descendants());
+            }
+        }
+        elements
+    }
+}
+
+/// the attributes are sorted by name and each child is on its own line,
+/// except in a text element or an element with text where the whitespace in between the children would be drawn
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}", self.name)?;
+        let mut attributes: Vec<&(String, String)> = self.attributes.iter().collect();
+        attributes.sort_by_key(|a| a.0.as_str());
+        for (name, value) in attributes{
+            write!(f, " {}=\"{}\"", name, escape_attribute(value))?;
+        }
+        if self.children.is_empty(){
+            return write!(f, "/>");
+        }
+        write!(f, ">")?;
+        let has_text = self.children.iter().any(|child| match *child{
+            Content::Text(_) => true,
+            Content::Element(_) => false,
+        });
+        let separator = if has_text || self.name == "text"{ "" } else { "\n" };
+        for child in &self.children{
+            write!(f, "{}{}", separator, child)?;
+        }
+        write!(f, "{}</{}>", separator, self.name)
+    }
+}
+
+impl fmt::Display for Content {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self{
+            Content::Element(ref element) => write!(f, "{}", element),
+            Content::Text(ref text) => write!(f, "{}", escape_str(text)),
+        }
+    }
+}
+
+/// escape the characters that can't be in a double quoted attribute value
+fn escape_attribute(s: &str) -> String {
+    s.chars().map(to_xml_char).collect::<String>()
+        .replace('&', "&amp;").replace('<', "&lt;").replace('"', "&quot;")
+}
+
 enum Anchor{
     Start,
     Middle,
@@ -42,32 +569,68 @@ enum Anchor{
 }
 
 
+/// The memes parsed from the whole text
 #[derive(Debug)]
-struct Body{
-    memes: Vec<Meme>,
-    rest_str: Vec<(usize, String)>
+pub struct Document{
+    /// the body of each line
+    pub lines: Vec<Body>,
+}
+
+/// The memes of a line and the rest of the text that is not a part of the memes
+#[derive(Debug)]
+pub struct Body{
+    /// the memes in the order they appear in the line
+    pub memes: Vec<Meme>,
+    /// the column and the text of each run of the rest of the text
+    pub rest_str: Vec<(usize, String)>,
+    /// the byte offset of the line in the parsed text, which the spans of the memes count from
+    pub offset: usize,
 }
 
 impl Body {
 
 
-    fn has_memes(&self) -> bool{
+    pub fn has_memes(&self) -> bool{
         !self.memes.is_empty()
     }
     
-    fn get_svg_elements(&self, y: usize, settings: &Settings) -> Vec<Box<Node>>{
-        let mut svg:Vec<Box<Node>> = vec![];
-        for meme in &self.memes{
-            svg.extend(meme.get_svg_elements(y, settings));
+    /// each meme is a group of its parts
+    fn get_svg_elements(&self, row: &Row, options: &RenderOptions) -> Vec<Element>{
+        self.memes.iter()
+            .map(|meme| meme.get_svg_group(row, options))
+            .collect()
+    }
+
+    /// the memes of the line, preceded by the rest of the text when it is drawn
+    fn get_line_svg_elements(&self, row: &Row, options: &RenderOptions) -> Vec<Element>{
+        let mut elements = vec![];
+        if options.rest_text{
+            elements.extend(self.get_rest_svg_elements(row, options));
+        }
+        elements.extend(self.get_svg_elements(row, options));
+        elements
+    }
+
+    /// the text that is not a part of a meme, each run is drawn at the column of its first non space character
+    fn get_rest_svg_elements(&self, row: &Row, options: &RenderOptions) -> Vec<Element>{
+        let mut svg = vec![];
+        for &(x, ref word) in &self.rest_str{
+            if options.grid_fit != GridFit::Off{
+                svg.extend(to_svg_grid_text(word, x, row, options, "rest"));
+                continue;
+            }
+            for (column, run) in space_runs(word, x){
+                svg.push(to_svg_text(run, column, row, options, Anchor::Start, "rest"));
+            }
         }
         svg
     }
 
-    // build the rest text in 1 string
-    fn unify_rest_text(&self) -> String{
+    /// build the rest text in 1 string
+    pub fn unify_rest_text(&self) -> String{
         let mut unify = String::new();
         for &(sx, ref word) in &self.rest_str{
-            let lacks  = sx - unify.width();
+            let lacks  = sx - text_width(&unify);
             if lacks > 0{
                 for _ in 0..lacks{
                     unify.push(' ')
@@ -84,428 +647,1774 @@ impl Body {
 /// The whole meme body
 /// 
 #[derive(Clone,Debug)]
-struct Meme{
+pub struct Meme{
     /// location to the left until a space is encountered
-    start_position: usize,
-    head: Head,
+    pub start_position: usize,
+    /// byte range of the whole meme in the parsed text, including both sides
+    pub span: Range<usize>,
+    pub head: Head,
     /// location to the right until a space is encoutered
-    end_position: usize,
+    pub end_position: usize,
     /// string at the left
-    left_side: String,
+    pub left_side: String,
+    /// byte range of the string at the left
+    pub left_span: Range<usize>,
     /// string at the right
-    right_side: String
+    pub right_side: String,
+    /// byte range of the string at the right
+    pub right_span: Range<usize>,
+}
+
+impl Meme{
+
+    /// the whole text of the meme, including both sides
+    pub fn text(&self) -> String {
+        let head: String = self.head.get_glyphs().into_iter().map(|g| g.text).collect();
+        format!("{}{}{}", self.left_side, head, self.right_side)
+    }
+
+    /// the human description of the meme such as "table flip",
+    /// looked up by the whole text of the meme and then by its face
+    pub fn name(&self) -> Option<&'static str> {
+        let text = self.text();
+        let text = text.trim();
+        MEME_NAMES.iter()
+            .find(|&&(meme, _)| meme == text)
+            .or_else(|| MEME_NAMES.iter().find(|&&(face, _)| face == self.head.face))
+            .map(|&(_, name)| name)
+    }
+    
+    /// the column where the meme starts, which is the start of the left side
+    pub fn start_column(&self) -> usize {
+        self.head.startx - text_width(&self.left_side)
+    }
+
+    /// the parts of the meme in a group identified by the line and column of the meme,
+    /// the group is an image labeled with the name of the meme for screen readers
+    fn get_svg_group(&self, row: &Row, options: &RenderOptions) -> Element {
+        let column = self.start_column();
+        let mut group = Element::new("g")
+            .set("id", format!("meme-{}-{}", row.index, column))
+            .set("class", "meme")
+            .set("data-line", row.index)
+            .set("data-col", column)
+            .set("data-face", &self.head.face);
+        if options.accessible{
+            group.assign("role", "img");
+            group.assign("aria-label", self.name().unwrap_or(UNNAMED_MEME));
+        }
+        group.children = self.get_svg_elements(row, options).into_iter()
+            .map(Content::Element)
+            .collect();
+        group
+    }
+
+    fn get_svg_elements(&self, row: &Row, options: &RenderOptions) -> Vec<Element>{
+        let mut elements = vec![];
+        if options.grid_fit != GridFit::Off{
+            elements.extend(to_svg_grid_text(&self.left_side, self.start_column(), row, options, "arm"));
+            elements.extend(self.head.get_svg_elements(row, options));
+            elements.extend(to_svg_grid_text(&self.right_side, self.head.end_column(), row, options, "arm"));
+            return elements;
+        }
+        let left_text = to_svg_text(&self.left_side, self.head.startx, row, options, Anchor::End, "arm");
+        elements.push(left_text);
+        elements.extend(self.head.get_svg_elements(row, options));
+        let right_text = to_svg_text(&self.right_side, self.head.endx, row, options, Anchor::Start, "arm");
+        elements.push(right_text);
+        elements
+    }
+
+    
+}
+
+fn to_svg_text(s: &str, x: usize, row: &Row, options: &RenderOptions, anchor: Anchor, class: &str) -> Element {
+    let px = x as f32 * options.text_width;
+    to_svg_text_pixel(s, px, row.top, options, anchor, class)
+}
+
+fn to_svg_text_pixel(s: &str, x: f32, y: f32, options: &RenderOptions, anchor: Anchor, class: &str) -> Element {
+    to_svg_text_content(vec![Content::Text(s.to_string())], x, y, options, anchor, class)
+}
+
+fn to_svg_text_content(content: Vec<Content>, x: f32, y: f32, options: &RenderOptions, anchor: Anchor, class: &str) -> Element {
+    let (offsetx, offsety) = options.offset();
+    let sx = x + offsetx;
+    let sy = y + options.text_height * options.baseline + offsety;
+    let mut svg_text = Element::new("text")
+        .set("x", sx)
+        .set("y", sy)
+        .set("class", class);
+    match anchor{
+        Anchor::Start => {
+            svg_text.assign("text-anchor", "start");
+        }
+        Anchor::Middle => {
+            svg_text.assign("text-anchor", "middle");
+        }
+        Anchor::End => {
+            svg_text.assign("text-anchor", "end");
+        }
+    };
+
+    svg_text.children = content;
+    svg_text
+}
+
+/// the text fitted to the grid of cells starting at the column, the spaces are not drawn.
+/// Each word is a text element, so the collapsed spaces don't shift the words after them
+fn to_svg_grid_text(s: &str, column: usize, row: &Row, options: &RenderOptions, class: &str) -> Vec<Element> {
+    let mut elements = vec![];
+    let mut x = column;
+    for word in s.split(' '){
+        if !word.is_empty(){
+            let glyphs: Vec<Glyph> = word.graphemes(true)
+                .scan(x, |gx, g| {
+                    let glyph = Glyph{x: *gx, text: g.to_string(), bracket: false};
+                    *gx += grapheme_width(g);
+                    Some(glyph)
+                })
+                .collect();
+            elements.push(to_svg_glyphs(&glyphs, row, options, class));
+        }
+        x += text_width(word) + 1;
+    }
+    elements
+}
+
+/// the runs of the text that are more than 1 space apart, together with the column each run starts at.
+/// The spaces in a text element are collapsed, so the runs are placed on their own to keep the gaps in between
+fn space_runs(s: &str, column: usize) -> Vec<(usize, &str)> {
+    let mut runs = vec![];
+    let mut run: Option<(usize, Range<usize>)> = None;
+    let mut x = column;
+    let mut gap = 0;
+    for (i, g) in s.grapheme_indices(true){
+        if g == " "{
+            gap += 1;
+        }else{
+            match run{
+                Some((_, ref mut range)) if gap <= 1 => range.end = i + g.len(),
+                _ => {
+                    runs.extend(run.take().map(|(rx, range)| (rx, &s[range])));
+                    run = Some((x, i..i + g.len()));
+                }
+            }
+            gap = 0;
+        }
+        x += grapheme_width(g);
+    }
+    runs.extend(run.map(|(rx, range)| (rx, &s[range])));
+    runs
+}
+
+/// the glyphs in a text element that starts at the first glyph,
+/// positioned depending on how the text is fitted to the grid
+fn to_svg_glyphs(glyphs: &[Glyph], row: &Row, options: &RenderOptions, class: &str) -> Element {
+    let start = glyphs.first().map_or(0, |g| g.x);
+    let end = glyphs.last().map_or(0, |g| g.x + grapheme_width(&g.text));
+    let px = start as f32 * options.text_width;
+    let svg_text = to_svg_text_content(glyph_content(glyphs, options), px, row.top, options, Anchor::Start, class);
+    match options.grid_fit{
+        GridFit::TextLength => svg_text
+            .set("textLength", (end - start) as f32 * options.text_width)
+            .set("lengthAdjust", "spacingAndGlyphs"),
+        _ => svg_text,
+    }
+}
+
+/// the glyphs where the brackets are wrapped in tspan,
+/// every glyph is wrapped in a tspan at its x location when fitting each glyph to the grid.
+/// The marks combined with a bracket are left out of its tspan, so they are drawn while the bracket is hidden
+fn glyph_content(glyphs: &[Glyph], options: &RenderOptions) -> Vec<Content> {
+    let (offsetx, _) = options.offset();
+    let mut pieces = vec![];
+    for glyph in glyphs{
+        match glyph.text.char_indices().nth(1){
+            Some((i, _)) if glyph.bracket => {
+                pieces.push((glyph.x, &glyph.text[..i], true));
+                pieces.push((glyph.x, &glyph.text[i..], false));
+            }
+            _ => pieces.push((glyph.x, glyph.text.as_str(), glyph.bracket)),
+        }
+    }
+    let mut content = vec![];
+    for (x, text, bracket) in pieces{
+        let mut tspan = Element::new("tspan");
+        if bracket{
+            tspan.assign("class", "head");
+        }
+        if options.grid_fit == GridFit::Glyph{
+            if text.trim().is_empty(){
+                continue;
+            }
+            let px = x as f32 * options.text_width + offsetx;
+            content.push(Content::Element(tspan.set("x", px).text(text)));
+        }
+        else if bracket{
+            content.push(Content::Element(tspan.text(text)));
+        }
+        else if let Some(&mut Content::Text(ref mut previous)) = content.last_mut(){
+            previous.push_str(text);
+        }
+        else{
+            content.push(Content::Text(text.to_string()));
+        }
+    }
+    content
+}
+
+/// a grapheme at its column
+struct Glyph{
+    x: usize,
+    text: String,
+    bracket: bool,
+}
+
+
+/// The head of the meme
+/// the face is the string in between
+/// used in detecting if it's a valid meme or not
+#[derive(Clone,Debug)]
+pub struct Head{
+    /// grapheme position
+    pub start_position: usize,
+    /// byte range of the head in the parsed text, including the brackets
+    pub span: Range<usize>,
+    /// left x location x1
+    pub startx: usize,
+    /// the string in between the brackets
+    pub face: String,
+    /// byte range of the face in the parsed text
+    pub face_span: Range<usize>,
+    /// right x location x2
+    pub endx: usize,
+    /// end position
+    pub end_position: usize,
+    /// the bracket pair enclosing the face, none for a face without brackets
+    pub bracket: Option<Bracket>,
+    /// the close bracket followed by the marks combined with it, empty for a face without brackets
+    pub close_grapheme: String,
+    /// the heads nested inside the face
+    pub heads: Vec<Head>,
+}
+
+impl Head{
+
+    pub fn distance(&self) -> usize {
+        self.endx - self.startx     
+    }
+
+    fn get_svg_elements(&self, row: &Row, options: &RenderOptions) -> Vec<Element> {
+        let mut elements = self.get_outlines(row, options);
+        elements.push(self.get_face_text(row, options));
+        elements
+    }
+
+    /// the outline of this head followed by the outlines of the nested heads,
+    /// so the inner outlines are drawn on top of the outer ones
+    fn get_outlines(&self, row: &Row, options: &RenderOptions) -> Vec<Element> {
+        let mut outlines = vec![self.get_outline(row, options)];
+        for head in &self.heads{
+            outlines.extend(head.get_outlines(row, options));
+        }
+        outlines
+    }
+
+    /// the outline of this head and the nested heads before the layout, keyed by their position
+    fn calc_outlines(&self, options: &RenderOptions) -> Vec<(usize, Outline)> {
+        let mut outlines = vec![(self.start_position, self.calc_outline(options))];
+        for head in &self.heads{
+            outlines.extend(head.calc_outlines(options));
+        }
+        outlines
+    }
+
+    fn get_face_text(&self, row: &Row, options: &RenderOptions) -> Element{
+        let mut text = if options.grid_fit != GridFit::Off{
+            to_svg_glyphs(&self.get_glyphs(), row, options, "face")
+        }else{
+            let c = row.outline(self, options);
+            to_svg_text_content(glyph_content(&self.get_glyphs(), options), c.cx, row.top, options, Anchor::Middle, "face")
+        };
+        if let Some(face) = self.get_style(options).and_then(|style| style.face){
+            text.assign("style", format!("fill: {};", face));
+        }
+        text
+    }
+
+    /// the style the colorizer picks for this face
+    fn get_style(&self, options: &RenderOptions) -> Option<HeadStyle> {
+        options.colorizer.as_ref()
+            .and_then(|colorizer| colorizer.colorize(&self.face))
+    }
+
+    /// the column right after the close bracket
+    fn end_column(&self) -> usize {
+        self.get_glyphs().last()
+            .map_or(self.startx, |g| g.x + grapheme_width(&g.text))
+    }
+
+    /// the graphemes of the face enclosed in its brackets at their column,
+    /// including the brackets of the nested heads
+    fn get_glyphs(&self) -> Vec<Glyph> {
+        let mut glyphs = vec![];
+        let mut x = self.startx;
+        let mut face = self.face.as_str();
+        if let Some(bracket) = self.bracket{
+            // the face starts with the marks that are combined with the open bracket
+            let marks = format!("{}{}", bracket.open, face).graphemes(true).next()
+                .map_or(0, |open| open.len() - bracket.open.len_utf8());
+            let open = format!("{}{}", bracket.open, &face[..marks]);
+            face = &face[marks..];
+            x += grapheme_width(&open);
+            glyphs.push(Glyph{x: self.startx, text: open, bracket: true});
+        }
+        let mut graphemes = face.graphemes(true);
+        let mut position = self.start_position + 1;
+        for head in &self.heads{
+            while position < head.start_position{
+                if let Some(g) = graphemes.next(){
+                    glyphs.push(Glyph{x, text: g.to_string(), bracket: false});
+                    x += grapheme_width(g);
+                }
+                position += 1;
+            }
+            glyphs.extend(head.get_glyphs());
+            x = head.end_column();
+            for _ in head.start_position..head.end_position + 1{
+                graphemes.next();
+            }
+            position = head.end_position + 1;
+        }
+        for g in graphemes{
+            glyphs.push(Glyph{x, text: g.to_string(), bracket: false});
+            x += grapheme_width(g);
+        }
+        if self.bracket.is_some(){
+            glyphs.push(Glyph{x, text: self.close_grapheme.clone(), bracket: true});
+        }
+        glyphs
+    }
+
+    /// the outline of the head, located relative to the top of its line
+    fn calc_outline(&self, options: &RenderOptions) -> Outline {
+        let text_width = options.text_width;
+        let text_height = options.text_height;
+        let radius = self.distance() as f32 / 2.0;
+        let center = self. startx as f32 + radius;
+        let cx = center * text_width; 
+        let cy = text_height / 2.0;
+        let rx = radius * text_width;
+        let shape = options.head_shape.infer(self.bracket);
+        let ry = match shape{
+            HeadShape::Circle => rx,
+            _ => text_height / 2.0,
+        };
+        Outline{
+            shape,
+            cx,
+            cy,
+            rx,
+            ry,
+        }
+    }
+
+    fn get_outline(&self, row: &Row, options: &RenderOptions)-> Element{
+        let o = row.outline(self, options);
+        let (offsetx, offsety) = options.offset();
+        let cx = o.cx + offsetx;
+        let cy = row.top + o.cy + offsety;
+        let style = self.get_style(options).map(|style| style.to_css());
+        let mut outline = match o.shape{
+            HeadShape::Ellipse => Element::new("ellipse")
+                .set("cx", cx)
+                .set("cy", cy)
+                .set("rx", o.rx)
+                .set("ry", o.ry),
+            HeadShape::RoundedRect => Element::new("rect")
+                .set("x", cx - o.rx)
+                .set("y", cy - o.ry)
+                .set("width", o.rx * 2.0)
+                .set("height", o.ry * 2.0)
+                .set("rx", o.ry / 2.0),
+            _ => Element::new("circle")
+                .set("cx", cx)
+                .set("cy", cy)
+                .set("r", o.rx),
+        };
+        outline.assign("class", "donger");
+        if let Some(style) = style{
+            outline.assign("style", style);
+        }
+        outline
+    }
+
+}
+
+/// the outline of a head as its center and its radius along each axis,
+/// the center is relative to the top of the line
+#[derive(Clone,Debug)]
+struct Outline{
+    shape: HeadShape,
+    cx: f32,
+    cy: f32,
+    rx: f32,
+    ry: f32,
+}
+
+impl Outline{
+
+    fn bounds(&self, top: f32) -> Bounds {
+        Bounds{
+            left: self.cx - self.rx,
+            top: top + self.cy - self.ry,
+            right: self.cx + self.rx,
+            bottom: top + self.cy + self.ry,
+        }
+    }
+
+    /// the bounds of the outline and the half of its stroke that lies outside of it
+    fn stroke_bounds(&self, top: f32, options: &RenderOptions) -> Bounds {
+        self.bounds(top).inflate(options.head_stroke_width / 2.0)
+    }
+
+    /// reduce the vertical radius, a circle that is reduced becomes an ellipse
+    fn clamp(&mut self, ry: f32) {
+        if ry < self.ry{
+            self.ry = ry;
+            if self.shape == HeadShape::Circle{
+                self.shape = HeadShape::Ellipse;
+            }
+        }
+    }
+}
+
+/// the rectangle that encloses an element of the drawing
+#[derive(Clone,Copy,Debug,PartialEq)]
+struct Bounds{
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+}
+
+impl Bounds{
+
+    /// the bounds that only touch each other don't overlap
+    fn overlaps(&self, other: &Bounds) -> bool {
+        self.left < other.right && other.left < self.right
+            && self.top < other.bottom && other.top < self.bottom
+    }
+
+    /// grown by the margin on every side
+    fn inflate(&self, margin: f32) -> Bounds {
+        Bounds{
+            left: self.left - margin,
+            top: self.top - margin,
+            right: self.right + margin,
+            bottom: self.bottom + margin,
+        }
+    }
+
+    fn union(&self, other: &Bounds) -> Bounds {
+        Bounds{
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+}
+
+/// the placement of a line in the drawing after the layout pass
+struct Row{
+    /// the line number
+    index: usize,
+    /// the y location of the top of the line
+    top: f32,
+    /// the resolved outline of the heads in the line, keyed by the position of the head
+    outlines: Vec<(usize, Outline)>,
+}
+
+impl Row{
+
+    fn outline(&self, head: &Head, options: &RenderOptions) -> Outline {
+        self.outlines.iter()
+            .find(|&&(position, _)| position == head.start_position)
+            .map(|(_, outline)| outline.clone())
+            .unwrap_or_else(|| head.calc_outline(options))
+    }
+}
+
+/// the bounds of the words in the line
+fn text_bounds(line: &str, top: f32, options: &RenderOptions) -> Vec<Bounds> {
+    to_cells(line, 0).split(|c| c.ch == ' ')
+        .filter_map(|word| match (word.first(), word.last()){
+            (Some(first), Some(last)) => Some(Bounds{
+                left: first.x as f32 * options.text_width,
+                top,
+                right: (last.x + last.width) as f32 * options.text_width,
+                bottom: top + options.text_height,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// the layout pass, which places the lines and resolves the outline of the heads
+/// that collide with the text or the heads of the other lines
+fn layout(lines: &[&str], bodies: &[Body], options: &RenderOptions) -> Vec<Row> {
+    let text_height = options.text_height;
+    let mut outlines: Vec<Vec<(usize, Outline)>> = bodies.iter()
+        .map(|body| body.memes.iter().flat_map(|meme| meme.head.calc_outlines(options)).collect())
+        .collect();
+    // the text and the heads of each line, where the lines are not yet spaced apart
+    let obstacles: Vec<Vec<Bounds>> = lines.iter().zip(&outlines).enumerate()
+        .map(|(y, (line, heads))| {
+            let top = y as f32 * text_height;
+            let mut bounds = text_bounds(line, top, options);
+            bounds.extend(heads.iter().map(|(_, o)| o.bounds(top)));
+            bounds
+        })
+        .collect();
+    let mut above = vec![0.0; lines.len()];
+    let mut below = vec![0.0; lines.len()];
+    for (y, heads) in outlines.iter_mut().enumerate(){
+        let top = y as f32 * text_height;
+        for (_, o) in heads.iter_mut(){
+            let bounds = o.bounds(top);
+            let center = top + o.cy;
+            let hits: Vec<&Bounds> = obstacles.iter().enumerate()
+                .filter(|&(other, _)| other != y)
+                .flat_map(|(_, b)| b.iter())
+                .filter(|b| b.overlaps(&bounds))
+                .collect();
+            if hits.is_empty(){
+                continue;
+            }
+            match options.overlap{
+                Overlap::Allow => (),
+                Overlap::Clamp => {
+                    let room = hits.iter()
+                        .map(|h| if h.bottom <= center{
+                                center - h.bottom
+                            }else if h.top >= center{
+                                h.top - center
+                            }else{
+                                0.0
+                            })
+                        .fold(o.ry, f32::min);
+                    o.clamp(room.max(text_height / 2.0));
+                }
+                Overlap::Ellipse => o.clamp(text_height / 2.0),
+                Overlap::Spacing => {
+                    let excess = o.ry - text_height / 2.0;
+                    if hits.iter().any(|h| h.top < center){
+                        above[y] = f32::max(above[y], excess);
+                    }
+                    if hits.iter().any(|h| h.bottom > center){
+                        below[y] = f32::max(below[y], excess);
+                    }
+                }
+            }
+        }
+    }
+    let mut top = 0.0;
+    outlines.into_iter().enumerate()
+        .map(|(y, outlines)| {
+            top += above[y];
+            let row = Row{index: y, top, outlines};
+            top += text_height + below[y];
+            row
+        })
+        .collect()
+}
+
+/// the bounds of the whole drawing, which encloses every line and the stroked outline of every head
+fn content_bounds(lines: &[&str], rows: &[Row], options: &RenderOptions) -> Bounds {
+    lines.iter().zip(rows)
+        .fold(Bounds{left: 0.0, top: 0.0, right: 0.0, bottom: 0.0}, |bounds, (line, row)| {
+            bounds.union(&line_bounds(line, row, options))
+        })
+}
+
+/// the bounds of the text of a line and the outline of its heads, together with their stroke
+fn line_bounds(line: &str, row: &Row, options: &RenderOptions) -> Bounds {
+    let text = Bounds{
+        left: 0.0,
+        top: row.top,
+        right: text_width(line) as f32 * options.text_width,
+        bottom: row.top + options.text_height,
+    };
+    row.outlines.iter()
+        .fold(text, |bounds, (_, o)| bounds.union(&o.stroke_bounds(row.top, options)))
+}
+
+
+/// the total score at which a face is considered a meme
+pub const MEME_THRESHOLD: f32 = 1.0;
+
+/// the faces up to this width are not penalized
+const WIDE_FACE_WIDTH: usize = 10;
+
+/// the faces are accepted however wide they are
+const MAX_FACE_WIDTH: usize = usize::MAX;
+
+/// the arms are attached to the head up to where they end, however wide they are
+const MAX_ARM_WIDTH: usize = usize::MAX;
+
+/// Options that control which faces and arms the parser accepts
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseOptions {
+    /// the bracket pairs that may enclose a face
+    pub brackets: Vec<Bracket>,
+    /// the faces wider than this are not memes however they score,
+    /// they are not limited by default
+    pub max_face_width: usize,
+    /// the arms on either side of the head are cut at this width,
+    /// they are not cut by default
+    pub max_arm_width: usize,
+    /// whether a single space in between symbols such as ╯︵ ┻━┻ is part of the arm,
+    /// a space next to another space or an ascii letter or digit still ends the arm
+    pub arms_cross_spaces: bool,
+    /// the total score at which a face is considered a meme
+    pub threshold: f32,
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            brackets: BRACKETS.to_vec(),
+            max_face_width: MAX_FACE_WIDTH,
+            max_arm_width: MAX_ARM_WIDTH,
+            arms_cross_spaces: false,
+            threshold: MEME_THRESHOLD,
+        }
+    }
+}
+
+/// the pairs of characters that mirror each other when used as the eyes of a face
+const MIRRORED: [(char, char); 8] = [
+    ('ò', 'ó'), ('(', ')'), ('<', '>'), ('/', '\\'),
+    ('╰', '╯'), ('┌', '┐'), ('ᕦ', 'ᕤ'), ('ᕙ', 'ᕗ'),
+];
+
+/// The score of how likely a string is the face of a meme,
+/// broken down into the contribution of each feature
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct MemeScore{
+    /// has at least 1 character with unicode value way up high
+    pub high_codepoint: f32,
+    /// has at least 1 character below that, but still more than 1 byte in size
+    pub non_ascii: f32,
+    /// has at least 1 zero sized width character (width = 0)
+    pub zero_width: f32,
+    /// has at least 1 full width character (width = 2)
+    pub fullwidth: f32,
+    /// the eyes at both ends are the same or mirror each other
+    pub symmetry: f32,
+    /// positive when it can't be an expression, negative when it is an expression in ascii
+    pub expression: f32,
+    /// negative when it reads like words written in the letters of a script
+    pub prose: f32,
+    /// negative for every column of a face wider than 10 columns
+    pub width: f32,
+    /// the kind of technical text it was recognized as, which is never a meme
+    pub technical: Option<Technical>,
+}
+
+/// The technical text that is often enclosed in brackets in the docs
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Technical{
+    /// identifiers and paths such as foo_bar, foo::bar or src/lib.rs,
+    /// and patterns with placeholders such as _ or _, b
+    Identifier,
+    Url,
+    Email,
+    /// math using greek letters such as α+β or λx.x
+    Math,
+    /// latex fragments such as \frac{a}{b}
+    Latex,
+}
+
+impl MemeScore{
+
+    pub fn total(&self) -> f32 {
+        self.high_codepoint + self.non_ascii + self.zero_width + self.fullwidth
+        + self.symmetry + self.expression + self.prose + self.width
+    }
+
+    pub fn is_meme(&self, threshold: f32) -> bool {
+        self.technical.is_none() && self.total() >= threshold
+    }
+}
+
+/// score the series of string on how likely it could be a meme,
+/// each column of a face wider than 10 columns lowers its score
+pub fn score_meme(ch: &str) -> MemeScore {
+    let mut score = MemeScore::default();
+    for c in ch.chars(){
+        let uw = c.width().unwrap_or(0);
+        if c as u32 >= 1000{
+            score.high_codepoint = 1.0;
+        }
+        else if c.len_utf8() >= 2 && uw > 0{
+            score.non_ascii = 0.5;
+        }
+        if uw == 0 && !c.is_whitespace(){
+            score.zero_width = 1.0;
+        }
+    }
+    if ch.graphemes(true).any(|g| grapheme_width(g) >= 2){
+        score.fullwidth = 0.5;
+    }
+    if is_symmetric(ch){
+        score.symmetry = 0.5;
+    }
+    if !is_expression(ch){
+        score.expression = 1.0;
+    }
+    else if ch.is_ascii(){
+        score.expression = -1.0;
+    }
+    if is_prose(ch){
+        score.prose = -2.0;
+    }
+    let total_width = text_width(ch);
+    if total_width > WIDE_FACE_WIDTH{
+        score.width = -0.25 * (total_width - WIDE_FACE_WIDTH) as f32;
+    }
+    score.technical = recognize_technical(ch);
+    score
+}
+
+/// the first and last visible characters are the same or mirror each other,
+/// needs at least 3 visible characters to have 2 eyes and something in between
+fn is_symmetric(ch: &str) -> bool {
+    let visible: Vec<char> = ch.chars()
+        .filter(|c| !c.is_whitespace() && c.width().unwrap_or(0) > 0)
+        .collect();
+    if visible.len() < 3{
+        return false;
+    }
+    let (first, last) = (visible[0], visible[visible.len() - 1]);
+    first == last || MIRRORED.iter().any(|&(l, r)| l == first && r == last)
+}
+
+/// The scripts whose letters are used in writing words
+#[derive(Clone,Copy,Debug,PartialEq)]
+enum Script{
+    Latin,
+    Greek,
+    Cyrillic,
+    // chinese characters together with japanese kana
+    Cjk,
+    Hangul,
+}
+
+/// the script of a letter that is used in writing words.
+/// The phonetic letters, the hangul compatibility jamo, the prolonged sound marks
+/// and the halfwidth sound marks are left out since those are mostly used as parts of faces
+fn word_script(c: char) -> Option<Script> {
+    if !c.is_alphabetic(){
+        return None;
+    }
+    match c as u32{
+        0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F => Some(Script::Latin),
+        0x370..=0x3FF | 0x1F00..=0x1FFF => Some(Script::Greek),
+        0x400..=0x52F => Some(Script::Cyrillic),
+        0x30FC => None,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF
+        | 0xFF66..=0xFF6F | 0xFF71..=0xFF9D => Some(Script::Cjk),
+        0x1100..=0x11FF | 0xAC00..=0xD7AF => Some(Script::Hangul),
+        _ => None,
+    }
+}
+
+/// the text reads like words rather than a face, such as an aside in japanese, chinese,
+/// korean, russian or greek enclosed in brackets.
+/// Most of the visible characters are letters, with at least 2 of them in the same script.
+/// A lone ideograph, hangul syllable or letter such as (株) or (Ж) is an aside too, but not a lone kana such as (ツ).
+/// Symmetric text such as ΘεΘ is still a face
+fn is_prose(ch: &str) -> bool {
+    let visible: Vec<char> = ch.chars()
+        .filter(|c| !c.is_whitespace() && c.width().unwrap_or(0) > 0)
+        .collect();
+    if let [c] = visible[..]{
+        let is_ideograph = matches!(c as u32, 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF);
+        let is_syllable = matches!(c as u32, 0xAC00..=0xD7AF);
+        let is_letter = matches!(word_script(c), Some(Script::Latin) | Some(Script::Greek) | Some(Script::Cyrillic));
+        return is_ideograph || is_syllable || is_letter;
+    }
+    let scripts: Vec<Script> = visible.iter().filter_map(|&c| word_script(c)).collect();
+    let most = [Script::Latin, Script::Greek, Script::Cyrillic, Script::Cjk, Script::Hangul].iter()
+        .map(|script| scripts.iter().filter(|s| *s == script).count())
+        .max()
+        .unwrap_or(0);
+    most >= 2 && scripts.len() * 5 >= visible.len() * 4 && !is_symmetric(ch)
+}
+
+/// detect whether the series of string could be a meme using the default threshold
+pub fn is_meme(ch: &str) -> bool{
+    score_meme(ch).is_meme(MEME_THRESHOLD)
+}
+
+/// the names of the well known memes, keyed by either the whole meme or its face
+const MEME_NAMES: [(&str, &str); 21] = [
+    ("¯\\_(ツ)_/¯", "shrug"),
+    ("¯＼(º_o)/¯", "I don't know"),
+    ("\\,,/(^_^)\\,,/", "rock on"),
+    ("╭∩╮(Ο_Ο)╭∩╮", "middle finger"),
+    ("┌∩┐(◣_◢)┌∩┐", "middle finger"),
+    ("ᕦ(ò_óˇ)ᕤ", "flexing"),
+    ("╯°□°", "table flip"),
+    ("ノಠ益ಠ", "table throw"),
+    (" ゜-゜ノ", "table put back"),
+    ("ಠ益ಠლ", "Y U NO"),
+    ("ಥ_ಥ", "crying look of disapproval"),
+    ("ಠ_ಠ", "look of disapproval"),
+    ("ツ", "happy face"),
+    ("-■_■", "shades"),
+    ("°,,°", "Zoidberg"),
+    (" ͡° ͜ʖ ͡°", "lenny face"),
+    ("ಠ۾ಠ", "professor"),
+    ("´סּ︵סּ`", "sad face"),
+    ("\\/", "Zoidberg claw"),
+    ("•ᴥ•", "bear"),
+    ("ຈل͜ຈ", "raise your dongers"),
+];
+
+/// the label of the memes that are not in the table of names
+const UNNAMED_MEME: &str = "emoticon";
+
+/// the characters that are a face by themselves even without the brackets
+const FACE_SYMBOLS: [char; 7] = ['ツ', 'ッ', 'シ', 'ヅ', '☺', '☻', '☹'];
+
+/// detect a face that is not enclosed in brackets,
+/// either a single face symbol such as ツ
+/// or an eye-mouth-eye pattern such as ಠ_ಠ that is also a meme
+fn is_bracketless_meme(word: &str, options: &ParseOptions) -> bool {
+    let chars: Vec<char> = word.chars().collect();
+    let face_symbol = chars.len() == 1 && FACE_SYMBOLS.contains(&chars[0]);
+    face_symbol || (is_eye_mouth_eye(&chars)
+        && text_width(word) <= options.max_face_width
+        && score_meme(word).is_meme(options.threshold))
+}
+
+/// the same eye at both ends with a short mouth in between,
+/// the mouth has no letters or digits of ascii.
+/// Box drawing characters are not eyes, so tables like ┻━┻ are not faces,
+/// and at least 1 of the characters is not ascii, so art like |_| or #_# is not a face without brackets
+fn is_eye_mouth_eye(chars: &[char]) -> bool {
+    if chars.len() < 3 || chars.len() > 5{
+        return false;
+    }
+    let eye = chars[0];
+    let mouth = &chars[1..chars.len() - 1];
+    eye == chars[chars.len() - 1]
+    && !chars.iter().all(|c| c.is_ascii())
+    && !eye.is_whitespace()
+    && !('\u{2500}'..='\u{257F}').contains(&eye)
+    && mouth.iter().all(|&m| m != eye && !m.is_whitespace() && !m.is_ascii_alphanumeric())
+}
+
+/// the display width of the text, counted per grapheme cluster
+fn text_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
+}
+
+/// the display width of a grapheme cluster,
+/// the combining marks in the cluster don't add up to its width
+fn grapheme_width(g: &str) -> usize {
+    if is_emoji_presentation(g){
+        2
+    }else{
+        g.chars().filter_map(|c| c.width()).max().unwrap_or(0)
+    }
+}
+
+/// emoji presentation sequences are 2 columns wide no matter how many characters they are made of:
+/// anything with the emoji variation selector, flags made of regional indicators,
+/// and emojis joined with zero width joiners or modified with skin tones
+fn is_emoji_presentation(g: &str) -> bool {
+    let is_emoji = |c: char| ('\u{1F000}'..='\u{1FAFF}').contains(&c) || ('\u{2600}'..='\u{27BF}').contains(&c);
+    let is_modifier = |c: char| c == '\u{200D}' || ('\u{1F3FB}'..='\u{1F3FF}').contains(&c);
+    g.chars().any(|c| c == '\u{FE0F}' || ('\u{1F1E6}'..='\u{1F1FF}').contains(&c))
+    || (g.chars().any(is_emoji) && g.chars().any(is_modifier))
+}
+
+/// return an SVG document base from the text infor string
+pub fn to_svg(s: &str, options: &RenderOptions) -> Element {
+    to_svg_with_view_box(s, options).0
+}
+
+/// the svg together with its view box
+fn to_svg_with_view_box(s: &str, options: &RenderOptions) -> (Element, Bounds) {
+    let mut svg = Element::new("svg")
+            .set("xmlns", "http://www.w3.org/2000/svg")
+            .set("font-size", options.font_size)
+            .set("font-family", options.font_family.as_str())
+            .child(Content::Element(get_styles(options)));
+    if options.accessible{
+        svg = svg.child(Content::Element(Element::new("title").text(s)));
+        let names: Vec<&str> = s.lines()
+            .flat_map(|line| parse_memes(line, &options.parse).memes)
+            .map(|meme| meme.name().unwrap_or(UNNAMED_MEME))
+            .collect();
+        if !names.is_empty(){
+            svg = svg.child(Content::Element(Element::new("desc").text(&names.join(", "))));
+        }
+    }
+
+    // the view box encloses the content that extends beyond the top and the left edge, plus the padding,
+    // and is at least 1 pixel in size so an empty text still makes an image
+    let (nodes, bounds) = to_svg_lines(s,options);
+    let (offsetx, offsety) = options.offset();
+    let view_box = Bounds{
+        left: bounds.left,
+        top: bounds.top,
+        right: f32::max(bounds.right + offsetx * 2.0, bounds.left + 1.0),
+        bottom: f32::max(bounds.bottom + offsety * 2.0, bounds.top + 1.0),
+    };
+    if let Some(background) = get_background(&view_box, options){
+        svg = svg.child(Content::Element(background));
+    }
+    svg.children.extend(nodes.into_iter().map(Content::Element));
+
+    let width = view_box.right - view_box.left;
+    let height = view_box.bottom - view_box.top;
+    svg.assign("viewBox", format!("{} {} {} {}", view_box.left, view_box.top, width, height));
+    if let Some(ref preserve_aspect_ratio) = options.preserve_aspect_ratio{
+        svg.assign("preserveAspectRatio", preserve_aspect_ratio);
+    }
+    match options.sizing{
+        Sizing::Pixels => {
+            svg.assign("width", width);
+            svg.assign("height", height);
+        }
+        Sizing::Em => {
+            svg.assign("width", format!("{}em", width / options.font_size));
+            svg.assign("height", format!("{}em", height / options.font_size));
+        }
+    }
+    (svg, view_box)
+}
+
+/// the fonts bundled for rasterizing and printing, covering the scripts and emojis commonly found in memes
+#[cfg(any(feature = "png", feature = "pdf"))]
+const FALLBACK_FONTS: [&[u8]; 4] = [
+    include_bytes!("../fonts/DejaVuSans.ttf"),
+    include_bytes!("../fonts/NotoSansKannada-Regular.ttf"),
+    include_bytes!("../fonts/NotoEmoji-Regular.ttf"),
+    include_bytes!("../fonts/MemenhancerFallback.ttf"),
+];
+
+/// the family used in place of a font family that is not bundled
+#[cfg(feature = "png")]
+const FALLBACK_FAMILY: &str = "DejaVu Sans";
+
+/// return the svg of the text rasterized into a png image.
+/// Only the bundled fonts are used, so the image is the same on every machine
+#[cfg(feature = "png")]
+pub fn to_png(s: &str, options: &RenderOptions) -> Vec<u8> {
+    rasterize(&to_svg(s, options)).encode_png().expect("the pixmap is encoded")
+}
+
+/// the pixels of the svg, drawn with the bundled fonts
+#[cfg(feature = "png")]
+fn rasterize(svg: &Element) -> resvg::tiny_skia::Pixmap {
+    use resvg::{usvg, tiny_skia};
+
+    let svg = join_text_runs(svg).to_string();
+    let mut usvg_options = usvg::Options{
+        font_family: FALLBACK_FAMILY.to_string(),
+        ..usvg::Options::default()
+    };
+    {
+        let fontdb = usvg_options.fontdb_mut();
+        for font in FALLBACK_FONTS.iter(){
+            fontdb.load_font_data(font.to_vec());
+        }
+        fontdb.set_serif_family(FALLBACK_FAMILY);
+        fontdb.set_sans_serif_family(FALLBACK_FAMILY);
+        fontdb.set_monospace_family(FALLBACK_FAMILY);
+    }
+    let tree = usvg::Tree::from_str(&svg, &usvg_options).expect("the generated svg is valid");
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).expect("the svg is not empty");
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap
+}
+
+/// the svg with the runs of each text that is not fitted glyph by glyph joined into one,
+/// where the hidden brackets are replaced by figure spaces as wide as their columns.
+/// The renderer drops or panics on the combining marks that are not in the first run of a text
+#[cfg(feature = "png")]
+fn join_text_runs(element: &Element) -> Element {
+    let is_run = |content: &Content| match *content{
+        Content::Element(ref tspan) => tspan.name == "tspan" && tspan.attribute("x").is_none(),
+        Content::Text(_) => true,
+    };
+    let mut joined = Element{children: vec![], ..element.clone()};
+    if element.name == "text" && element.children.iter().all(is_run){
+        let mut text = String::new();
+        for content in &element.children{
+            match *content{
+                Content::Element(ref tspan) => {
+                    let run: String = tspan.children.iter()
+                        .filter_map(|c| match *c{ Content::Text(ref t) => Some(t.as_str()), _ => None })
+                        .collect();
+                    if tspan.attribute("class") == Some("head"){
+                        text.extend(::std::iter::repeat_n('\u{2007}', text_width(&run)));
+                    }else{
+                        text.push_str(&run);
+                    }
+                }
+                Content::Text(ref run) => text.push_str(run),
+            }
+        }
+        joined.children.push(Content::Text(text));
+    }else{
+        for content in &element.children{
+            joined.children.push(match *content{
+                Content::Element(ref child) => Content::Element(join_text_runs(child)),
+                Content::Text(ref text) => Content::Text(text.clone()),
+            });
+        }
+    }
+    joined
+}
+
+/// the stylesheet generated from the theme or the replacement css, followed by the appended css
+fn get_styles(options: &RenderOptions) -> Element {
+    let theme = &options.theme;
+    let mut css = match options.css{
+        Some(ref css) => css.to_string(),
+        None => format!(r#"
+    line, path {{
+      stroke: {stroke_color};
+      stroke-width: {stroke};
+      stroke-opacity: 1;
+      fill-opacity: 1;
+      stroke-linecap: round;
+      stroke-linejoin: miter;
+    }}
+    circle {{
+      stroke: {stroke_color};
+      stroke-width: {stroke};
+      stroke-opacity: 1;
+      fill-opacity: 1;
+      stroke-linecap: round;
+      stroke-linejoin: miter;
+      fill: {head_fill};
+    }}
+    .donger{{
+       stroke: {stroke_color};
+       stroke-width: {head_stroke};
+       fill: {head_fill};
+    }}
+    text.face, text.arm, text.rest{{
+        fill: {text_color};
+    }}
+    tspan.head{{
+        fill: none;
+        stroke: none;
+    }}
+    rect.background{{
+        fill: {background};
+        stroke: none;
+    }}
+    "#,
+        stroke_color = theme.stroke,
+        stroke = options.stroke_width,
+        head_fill = theme.head_fill,
+        head_stroke = options.head_stroke_width,
+        text_color = theme.text,
+        background = theme.background.as_ref().map_or("none", |b| b.as_str()),
+        ),
+    };
+    if !css.ends_with('\n'){
+        css.push('\n');
+    }
+    Element::new("style").text(&(css + &options.extra_css))
+}
+
+/// the rectangle that fills the whole drawing with the background of the theme
+fn get_background(view_box: &Bounds, options: &RenderOptions) -> Option<Element> {
+    options.theme.background.as_ref().map(|_| {
+        Element::new("rect")
+            .set("class", "background")
+            .set("x", view_box.left)
+            .set("y", view_box.top)
+            .set("width", view_box.right - view_box.left)
+            .set("height", view_box.bottom - view_box.top)
+    })
+}
+
+/// process and parses each line,
+/// together with the bounds of the whole drawing
+fn to_svg_lines(s: &str, options: &RenderOptions) -> (Vec<Element>, Bounds) {
+    let lines: Vec<&str> = s.lines().collect();
+    let bodies: Vec<Body> = lines.iter().map(|line| parse_memes(line, &options.parse)).collect();
+    let rows = layout(&lines, &bodies, options);
+    let elements = bodies.iter().zip(&rows)
+        .flat_map(|(body, row)| body.get_line_svg_elements(row, options))
+        .collect();
+    (elements, content_bounds(&lines, &rows, options))
+}
+
+/// return the group of each meme and all the assembled rest of the string that is not a part of the memes.
+/// The lines of the rest of the string are evenly spaced, so the heads are clamped instead when the overlap is spacing
+pub fn get_meme_svg(input: &str, options: &RenderOptions) -> (Vec<Element>, String, Element) {
+    let mut options = options.clone();
+    if options.overlap == Overlap::Spacing{
+        options.overlap = Overlap::Clamp;
+    }
+    let options = &options;
+    let mut svg_elements = vec![];
+    let mut relines = String::new();
+    let lines: Vec<&str> = input.lines().collect();
+    let bodies: Vec<Body> = lines.iter().map(|line| parse_memes(line, &options.parse)).collect();
+    let rows = layout(&lines, &bodies, options);
+    for ((line, body), row) in lines.iter().zip(&bodies).zip(&rows){
+        if body.has_memes(){
+            relines.push_str(&body.unify_rest_text());
+            svg_elements.extend(body.get_svg_elements(row, options));
+        }else{
+            relines.push_str(line);
+        }
+        relines.push('\n');
+    } 
+    (svg_elements, relines, get_styles(options))
+}
+
+#[test]
+fn test_1line(){
+    let meme = "";
+    let (nodes, _) = to_svg_lines(meme, &RenderOptions::default());
+    assert_eq!(nodes.len(), 0);
+}
+
+#[test]
+fn test_render_options(){
+    let options = RenderOptions::new()
+        .cell_size(10.0, 20.0)
+        .font_family("monospace")
+        .font_size(16.0)
+        .padding(5.0, 4.0)
+        .baseline(0.5)
+        .stroke_width(3.0)
+        .head_stroke_width(1.5);
+    let svg = to_svg("(ツ)", &options).to_string();
+    assert!(svg.contains(r#"font-family="monospace""#));
+    assert!(svg.contains(r#"font-size="16""#));
+    assert!(svg.contains(r#"width="50.75""#));
+    assert!(svg.contains(r#"height="39.5""#));
+    // the view box encloses the half of the stroke that is outside of the circle
+    assert!(svg.contains(r#"viewBox="-0.75 -5.75 50.75 39.5""#));
+    assert!(svg.contains("stroke-width: 3;"));
+    assert!(svg.contains("stroke-width: 1.5;"));
+    // the face is centered in the circle and sits on the baseline at the middle of the cell
+    assert!(svg.contains(r#"cx="20" cy="14""#));
+    assert!(svg.contains(r#"x="20" y="14""#));
+}
+
+#[test]
+fn test_space_runs(){
+    assert_eq!(vec![(2, "no memes"), (14, "all"), (19, "ಠ ಠ")], space_runs("  no memes    all  ಠ ಠ ", 0));
+    assert_eq!(vec![(5, "a"), (8, "b")], space_runs("a  b", 5));
+    assert_eq!(Vec::<(usize, &str)>::new(), space_runs("   ", 0));
+    // the gap in between the words is kept in the drawing
+    let svg = to_svg("name:    value", &RenderOptions::new().rest_text(true)).to_string();
+    assert_eq!(2, svg.matches(r#"class="rest""#).count());
+}
+
+#[test]
+fn test_rest_text(){
+    let text = "look (ツ) here\n  no memes & all\n   \n";
+    let memes_only = to_svg(text, &RenderOptions::new()).to_string();
+    assert!(!memes_only.contains(r#"class="rest""#));
+    let svg = to_svg(text, &RenderOptions::new().rest_text(true)).to_string();
+    assert_eq!(3, svg.matches(r#"class="rest""#).count());
+    assert!(svg.contains(r#"<text class="rest" text-anchor="start" x="80" y="12">here</text>"#));
+    assert!(svg.contains(r#"x="16" y="28">no memes &amp; all</text>"#));
+}
+
+#[test]
+fn test_grid_fit_glyph(){
+    let options = RenderOptions::new().grid_fit(GridFit::Glyph).rest_text(true);
+    let svg = to_svg("a (ツ)~ b", &options).to_string();
+    assert!(svg.contains(r#"<tspan class="head" x="16">(</tspan><tspan x="24">ツ</tspan><tspan class="head" x="40">)</tspan>"#));
+    assert!(svg.contains(r#"<tspan x="48">~</tspan>"#));
+    assert!(svg.contains(r#"<tspan x="64">b</tspan>"#));
+    assert!(!svg.contains("text-anchor=\"end\""));
+}
+
+#[test]
+fn test_grid_fit_text_length(){
+    let options = RenderOptions::new().grid_fit(GridFit::TextLength);
+    let svg = to_svg("ヽ༼ຈل͜ຈ༽ﾉ raise", &options).to_string();
+    assert!(svg.contains(r#"<text class="arm" lengthAdjust="spacingAndGlyphs" text-anchor="start" textLength="16" x="0""#));
+    assert!(svg.contains(r#"<text class="face" lengthAdjust="spacingAndGlyphs" text-anchor="start" textLength="40" x="16""#));
+    assert!(svg.contains(r#"<text class="arm" lengthAdjust="spacingAndGlyphs" text-anchor="start" textLength="8" x="56""#));
+}
+
+#[test]
+fn test_end_column(){
+    let body = parse_memes("ʕ•（ツ）•ʔ ಠ_ಠ", &ParseOptions::default());
+    assert_eq!(10, body.memes[0].head.end_column());
+    assert_eq!(8, body.memes[0].head.heads[0].end_column());
+    assert_eq!(14, body.memes[1].head.end_column());
+}
+
+#[test]
+fn test_meme_name(){
+    let body = parse_memes("¯\\_(ツ)_/¯ (╯°□°)╯︵ ┻━┻ (ツ) (•_•) (◕_◕)", &ParseOptions::default());
+    assert_eq!("¯\\_(ツ)_/¯ ", body.memes[0].text());
+    assert_eq!(Some("shrug"), body.memes[0].name());
+    assert_eq!(Some("table flip"), body.memes[1].name());
+    assert_eq!(Some("happy face"), body.memes[2].name());
+    assert_eq!(None, body.memes[3].name());
+    // the face of the pistols alone is not the pistols
+    assert_eq!(None, body.memes[4].name());
+}
+
+#[test]
+fn test_accessible(){
+    let text = "flip (╯°□°)╯︵ it & (•_•)";
+    let plain = to_svg(text, &RenderOptions::new()).to_string();
+    assert!(!plain.contains("<title>"));
+    assert!(!plain.contains("role="));
+    let svg = to_svg(text, &RenderOptions::new().accessible(true)).to_string();
+    assert!(svg.contains("<title>flip (╯°□°)╯︵ it &amp; (•_•)</title>"));
+    assert!(svg.contains("<desc>table flip, emoticon</desc>"));
+    assert!(svg.contains(r#"<g aria-label="table flip" class="meme""#));
+    assert!(svg.contains(r#"<g aria-label="emoticon" class="meme""#));
+}
+
+#[test]
+fn test_meme_group(){
+    let (groups, _, _) = get_meme_svg("ok (ツ)\n  (╯°□°)╯", &RenderOptions::new());
+    assert_eq!(2, groups.len());
+    assert_eq!("g", groups[0].name);
+    assert_eq!(Some("meme-0-3"), groups[0].attribute("id"));
+    assert_eq!(Some("meme"), groups[0].attribute("class"));
+    assert_eq!(Some("ツ"), groups[0].attribute("data-face"));
+    assert_eq!(Some("1"), groups[1].attribute("data-line"));
+    assert_eq!(Some("2"), groups[1].attribute("data-col"));
+    assert_eq!(None, groups[1].attribute("role"));
+    let names: Vec<&str> = groups[0].descendants().iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(vec!["text", "ellipse", "text", "tspan", "tspan", "text"], names);
+    // the groups stay on the evenly spaced lines of the rest of the text
+    let text = "(ಠ_ಠ ಠ_ಠ ಠ_ಠ)\nbelow (ツ)";
+    let groups = |overlap| get_meme_svg(text, &RenderOptions::new().overlap(overlap)).0
+        .iter().map(|g| g.to_string()).collect::<Vec<String>>();
+    assert_eq!(groups(Overlap::Clamp), groups(Overlap::Spacing));
+    assert!(groups(Overlap::Spacing)[1].contains(r#"y="28""#));
+}
+
+#[test]
+fn test_xml_round_trip(){
+    let memes = String::from_utf8_lossy(include_bytes!("../memes.txt"));
+    let texts = [
+        include_str!("../examples/donger.mem"),
+        include_str!("../examples/emoji.mem"),
+        include_str!("../examples/meme.mem"),
+        include_str!("../examples/reddong.mem"),
+        &memes,
+    ];
+    let options = RenderOptions::new().rest_text(true).accessible(true);
+    let glyph_options = options.clone().grid_fit(GridFit::Glyph);
+    for line in texts.iter().flat_map(|text| text.lines()){
+        let svg = to_svg(line, &options).to_string();
+        let doc = roxmltree::Document::parse(&svg).unwrap_or_else(|e| panic!("{}: {}\n{}", e, line, svg));
+        let title = doc.descendants().find(|n| n.has_tag_name("title")).unwrap();
+        let expected: String = line.chars().map(to_xml_char).collect();
+        assert_eq!(expected.replace('\r', "\n"), title.text().unwrap_or(""));
+        let faces: Vec<String> = doc.descendants()
+            .filter(|n| n.attribute("class") == Some("face"))
+            .map(|n| n.descendants().filter(|t| t.is_text()).filter_map(|t| t.text()).collect())
+            .collect();
+        // the text of each face is the text of its head in the line, brackets included
+        let heads: Vec<String> = parse_memes(line, &options.parse).memes.iter()
+            .map(|meme| line[meme.head.span.clone()].chars().map(to_xml_char).collect())
+            .collect();
+        assert_eq!(heads, faces, "{}", line);
+        let svg = to_svg(line, &glyph_options).to_string();
+        assert!(roxmltree::Document::parse(&svg).is_ok(), "{}\n{}", line, svg);
+    }
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn test_png_empty(){
+    use resvg::tiny_skia::Pixmap;
+    for text in ["", "\n", " "].iter(){
+        let image = Pixmap::decode_png(&to_png(text, &RenderOptions::new())).unwrap();
+        assert!(image.width() >= 1 && image.height() >= 1);
+    }
 }
 
-impl Meme{
-    
-    fn get_svg_elements(&self, y: usize, settings: &Settings) -> Vec<Box<Node>>{
-        let mut elements:Vec<Box<Node>> = vec![];
-        let left_text = to_svg_text(&self.left_side, self.head.startx, y, settings, Anchor::End);
-        elements.push(Box::new(left_text));
-        elements.extend(self.head.get_svg_elements(y, settings));
-        let right_text = to_svg_text(&self.right_side, self.head.endx, y, settings, Anchor::Start);
-        elements.push(Box::new(right_text));
-        elements
+#[cfg(feature = "png")]
+#[test]
+fn test_png_marks(){
+    // the marks after the brackets are in the second run of the text of the face
+    let text = "٩(͡๏̯͡๏)۶ (•̪●) (ʘ̚ل͜ʘ̚) ( ͡° ͜ʖ ( ͡° ͜ʖ ͡°) ͜ʖ ͡°)";
+    for &grid_fit in [GridFit::Off, GridFit::TextLength, GridFit::Glyph].iter(){
+        assert!(!to_png(text, &RenderOptions::new().grid_fit(grid_fit)).is_empty());
     }
-
-    
 }
 
-fn to_svg_text(s: &str, x: usize, y: usize, settings: &Settings, anchor: Anchor) -> SvgText {
-    let px = x as f32 * settings.text_width;
-    let py = y as f32 * settings.text_height;
-    to_svg_text_pixel(s, px, py, settings, anchor)
+#[cfg(feature = "png")]
+#[test]
+fn test_png(){
+    use resvg::tiny_skia::Pixmap;
+    let png = to_png(include_str!("../examples/sample.mem"), &RenderOptions::new().rest_text(true));
+    let image = Pixmap::decode_png(&png).unwrap();
+    let reference = Pixmap::decode_png(include_bytes!("../screenshots/sample.png")).unwrap();
+    assert_eq!((reference.width(), reference.height()), (image.width(), image.height()));
+    // allow for the anti-aliasing to differ slightly in between versions of the renderer
+    let different = image.data().iter().zip(reference.data())
+        .filter(|&(a, b)| (*a as i32 - *b as i32).abs() > 16)
+        .count();
+    assert!(different * 100 < image.data().len(), "{} of {} bytes are different", different, image.data().len());
 }
 
-fn to_svg_text_pixel(s: &str, x: f32, y: f32, settings: &Settings, anchor: Anchor) -> SvgText {
-    to_svg_text_pixel_escaped(&escape_str(s), x, y, settings, anchor)
+#[test]
+fn test_theme(){
+    let light = to_svg("(ツ)", &RenderOptions::new()).to_string();
+    assert!(light.contains(r#"<rect class="background""#));
+    assert!(light.contains(r#"class="donger""#));
+    assert!(light.contains(r#"class="face""#));
+    assert!(light.contains(r#"class="arm""#));
+    let dark = to_svg("(ツ)", &RenderOptions::new().theme(Theme::dark())).to_string();
+    assert!(dark.contains("fill: #1e1e1e;"));
+    assert!(dark.contains("stroke: #e6e6e6;"));
+    let transparent = to_svg("(ツ)", &RenderOptions::new().theme(Theme::transparent())).to_string();
+    assert!(!transparent.contains("<rect"));
+    assert!(transparent.contains("fill: none;"));
 }
 
-fn to_svg_text_pixel_escaped(s: &str, x: f32, y: f32, settings: &Settings, anchor: Anchor) -> SvgText {
-    let (offsetx, offsety) = settings.offset();
-    let sx = x + offsetx;
-    let sy = y + settings.text_height * 3.0 / 4.0 + offsety;
-    let mut svg_text = SvgText::new()
-        .set("x", sx)
-        .set("y", sy);
-    match anchor{
-        Anchor::Start => {
-            svg_text.assign("text-anchor", "start");
-        }
-        Anchor::Middle => {
-            svg_text.assign("text-anchor", "middle");
-        }
-        Anchor::End => {
-            svg_text.assign("text-anchor", "end");
-        }
-    };
+#[test]
+fn test_palette(){
+    let palette = Palette::default();
+    assert_eq!(Some(HeadStyle::new("#ffd5d5", "red")), palette.colorize("ノಠ益ಠ"));
+    assert_eq!(Some(HeadStyle::new("#d5e5ff", "blue")), palette.colorize("ಥ_ಥ"));
+    assert_eq!(Some(HeadStyle::new("black", "black").face("white")), palette.colorize("■_■"));
+    assert_eq!(None, palette.colorize("ツ"));
+    let custom = palette.rule("ツ", HeadStyle{fill: Some("yellow".to_string()), ..Default::default()});
+    assert_eq!("fill: yellow;", custom.colorize("ツ").unwrap().to_css());
+}
 
-    let text_node = TextNode::new(s);
-    svg_text.append(text_node);
-    svg_text
+#[test]
+fn test_colorizer(){
+    let plain = to_svg("(ಠ益ಠ) (ツ)", &RenderOptions::new()).to_string();
+    assert!(!plain.contains("style=\"fill"));
+    let colored = to_svg("(ಠ益ಠ) (ツ)", &RenderOptions::new().colorizer(Palette::default())).to_string();
+    assert!(colored.contains(r#"style="fill: #ffd5d5;stroke: red;""#));
+    assert_eq!(1, colored.matches("style=\"").count());
+    // the face with shades is drawn in white on the black head
+    let shades = to_svg("(■_■)", &RenderOptions::new().colorizer(Palette::default())).to_string();
+    assert!(shades.contains(r#"style="fill: black;stroke: black;""#));
+    assert!(shades.contains(r#"<text class="face" style="fill: white;""#));
 }
 
+#[test]
+fn test_options_are_send_and_sync(){
+    fn assert_send_sync<T: Send + Sync>(){}
+    assert_send_sync::<ParseOptions>();
+    assert_send_sync::<RenderOptions>();
+}
 
-/// The head of the meme
-/// the face is the string in between
-/// used in detecting if it's a valid meme or not
-#[derive(Clone,Debug)]
-struct Head{
-    // character position
-    start_position: usize,
-    // left x location x1
-    startx: usize,
-    face: String,
-    // right x location x2
-    endx: usize,
-    // end position
-    end_position: usize
+#[test]
+fn test_head_shape(){
+    let meme = "(ಠ_ಠ ಠ_ಠ ಠ_ಠ)";
+    let circle = to_svg(meme, &RenderOptions::new()).to_string();
+    assert!(circle.contains(r#"<circle class="donger" cx="48" cy="8" r="48"/>"#));
+    assert!(circle.contains(r#"height="97""#));
+    let ellipse = to_svg(meme, &RenderOptions::new().head_shape(HeadShape::Ellipse)).to_string();
+    assert!(ellipse.contains(r#"<ellipse class="donger" cx="48" cy="8" rx="48" ry="8"/>"#));
+    assert!(ellipse.contains(r#"height="17""#));
+    let rect = to_svg(meme, &RenderOptions::new().head_shape(HeadShape::RoundedRect)).to_string();
+    assert!(rect.contains(r#"<rect class="donger" height="16" rx="4" width="96" x="0" y="0"/>"#));
 }
 
-impl Head{
+#[test]
+fn test_view_box(){
+    let meme = "(ಠ_ಠ ಠ_ಠ ಠ_ಠ)";
+    let svg = to_svg(meme, &RenderOptions::new()).to_string();
+    assert!(svg.contains(r#"viewBox="-0.5 -40.5 104.5 97""#));
+    assert!(svg.contains(r#"<rect class="background" height="97" width="104.5" x="-0.5" y="-40.5"/>"#));
+    assert!(!svg.contains("preserveAspectRatio"));
+    let options = RenderOptions::new()
+        .padding(8.0, 4.0)
+        .sizing(Sizing::Em)
+        .preserve_aspect_ratio("xMinYMin meet");
+    let svg = to_svg(meme, &options).to_string();
+    assert!(svg.contains(r#"viewBox="-0.5 -40.5 120.5 105""#));
+    assert!(svg.contains(r#"preserveAspectRatio="xMinYMin meet""#));
+    assert!(svg.contains(r#"width="8.607142em""#));
+    assert!(svg.contains(r#"height="7.5em""#));
+}
 
-    fn distance(&self) -> usize {
-        self.endx - self.startx     
-    }
+#[test]
+fn test_overlap(){
+    let meme = "(ಠ_ಠ ಠ_ಠ ಠ_ಠ)\n\nbelow";
+    let allow = to_svg(meme, &RenderOptions::new().overlap(Overlap::Allow)).to_string();
+    assert!(allow.contains(r#"<circle class="donger" cx="48" cy="8" r="48"/>"#));
+    assert!(allow.contains(r#"height="97""#));
+    let clamp = to_svg(meme, &RenderOptions::new()).to_string();
+    assert!(clamp.contains(r#"<ellipse class="donger" cx="48" cy="8" rx="48" ry="24"/>"#));
+    assert!(clamp.contains(r#"height="64.5""#));
+    let ellipse = to_svg(meme, &RenderOptions::new().overlap(Overlap::Ellipse)).to_string();
+    assert!(ellipse.contains(r#"<ellipse class="donger" cx="48" cy="8" rx="48" ry="8"/>"#));
+    // the line below is moved down out of the reach of the circle
+    let spacing = to_svg("(ಠ_ಠ ಠ_ಠ ಠ_ಠ)\nbelow", &RenderOptions::new().overlap(Overlap::Spacing)).to_string();
+    assert!(spacing.contains(r#"<circle class="donger" cx="48" cy="8" r="48"/>"#));
+    assert!(spacing.contains(r#"height="112.5""#));
+}
 
-    fn get_svg_elements(&self, y: usize, settings:&Settings) -> Vec<Box<Node>> {
-        let mut elements: Vec<Box<Node>> = vec![];
-        elements.push(Box::new(self.get_circle(y, settings)));
-        elements.push(Box::new(self.get_face_text(y, settings)));
-        elements
-    }
+#[test]
+fn test_overlap_far_apart(){
+    let meme = "(ಠ_ಠ ಠ_ಠ ಠ_ಠ)\n                       below";
+    let svg = to_svg(meme, &RenderOptions::new()).to_string();
+    assert!(svg.contains(r#"<circle class="donger" cx="48" cy="8" r="48"/>"#));
+    assert!(svg.contains(r#"width="224.5""#));
+}
 
-    fn get_face_text(&self, y:usize, settings: &Settings) -> SvgText{
-        let c = self.calc_circle(y, settings);
-        let sy = y as f32 * settings.text_height;
-        let face = format!("<tspan class='head'>(</tspan>{}<tspan class='head'>)</tspan>", escape_str(&self.face));
-        to_svg_text_pixel_escaped(&face, c.cx, sy, settings, Anchor::Middle)
-    }
+#[test]
+fn test_head_shape_from_bracket(){
+    let shape = HeadShape::Bracket;
+    assert_eq!(HeadShape::Ellipse, shape.infer(Some(BRACKETS[0])));
+    assert_eq!(HeadShape::RoundedRect, shape.infer(Some(BRACKETS[2])));
+    assert_eq!(HeadShape::RoundedRect, shape.infer(Some(Bracket{open: '[', close: ']'})));
+    assert_eq!(HeadShape::Circle, shape.infer(Some(BRACKETS[4])));
+    assert_eq!(HeadShape::Ellipse, shape.infer(None));
+    assert_eq!(HeadShape::Circle, HeadShape::Circle.infer(Some(BRACKETS[2])));
+}
 
-    fn calc_circle(&self, y:usize, settings: &Settings) -> Circle {
-        let text_width = settings.text_width;
-        let text_height = settings.text_height;
-        let radius = self.distance() as f32 / 2.0;
-        let center = self. startx as f32 + radius;
-        let cx = center * text_width; 
-        let cy = y as f32 * text_height + text_height / 2.0;
-        let cr = radius * text_width;
-        Circle{
-            cx: cx,
-            cy: cy,
-            r: cr
-        }
-    }
+#[test]
+fn test_theme_css(){
+    let appended = RenderOptions::new().append_css("text.face{ fill: red; }");
+    let svg = to_svg("(ツ)", &appended).to_string();
+    assert!(svg.contains(".donger{"));
+    assert!(svg.contains("text.face{ fill: red; }"));
+    let replaced = RenderOptions::new()
+        .replace_css("circle.donger{ fill: pink; }")
+        .append_css("text.face{ fill: red; }");
+    let svg = to_svg("(ツ)", &replaced).to_string();
+    assert!(!svg.contains("stroke-linecap"));
+    assert!(svg.contains("circle.donger{ fill: pink; }\ntext.face{ fill: red; }"));
+}
 
-    fn get_circle(&self, y: usize, settings: &Settings)-> SvgCircle{
-        let c = self.calc_circle(y, settings);
-        let (offsetx, offsety) = settings.offset();
-        SvgCircle::new()
-            .set("cx",c.cx + offsetx)
-            .set("cy", c.cy + offsety)
-            .set("class", "donger")
-            .set("r", c.r)
-    }
 
+/// a grapheme cluster in the line together with its location
+#[derive(Clone,Copy,Debug)]
+struct Cell<'a>{
+    // grapheme position
+    position: usize,
+    // byte offset in the parsed text
+    byte: usize,
+    // x location
+    x: usize,
+    // the number of columns it occupies
+    width: usize,
+    // the first character of the grapheme, used for matching brackets and spaces
+    ch: char,
+    text: &'a str,
 }
 
+/// a matched pair of brackets and the pairs matched inside of it
 #[derive(Debug)]
-struct Circle{
-    cx: f32,
-    cy: f32,
-    r: f32,
-}
-
-
-/// detect whether the series of string could be a meme
-/// has at least 1 full width character (width = 2)
-/// has at least 1 zero sized width character (width = 0)
-/// has at least 1 character that has more than 1 byte in size
-/// unicode value is way up high
-fn is_meme(ch: &str) -> bool{
-    let total_bytes = ch.len();
-    let total_width = ch.width();
-    let mut gte_bytes2 = 0; 
-    let mut gte_width2 = 0;
-    let mut zero_width = 0;
-    let mut gte_unicode_1k = 0;
-    for c in ch.chars(){
-        if c as u32 >= 1000{
-            gte_unicode_1k += 1;
-        }
-        if c.len_utf8() >= 2{
-            gte_bytes2 += 1;
-        }
-        if let Some(uw) = c.width(){
-            if uw >= 2 {
-                gte_width2 += 1;
-            }
-            if uw == 0 {
-                zero_width += 1;
-            }
-        }
-    }
-    /*
-    println!("total_bytes: {}", total_bytes);
-    println!("gte_bytes2: {}", gte_bytes2);
-    println!("gte_width2: {}", gte_width2);
-    println!("zero_width: {}", zero_width);
-    println!("gte_unicode_1k {}", gte_unicode_1k);
-    println!("total_width: {}", total_width);
-    println!("");
-    */
-    total_width <= 10 && // must be at most 10 character face
-    (gte_bytes2 > 0 || gte_width2 > 0
-    || zero_width > 0 || gte_unicode_1k > 0
-    || total_bytes > total_width
-    || !is_expression(ch)
-    )
+struct Span{
+    // character position of the open bracket
+    open: usize,
+    // character position of the close bracket
+    close: usize,
+    bracket: Bracket,
+    spans: Vec<Span>,
 }
 
+/// the byte offset of each character starts from the offset of the line in the parsed text
+fn to_cells(s: &str, offset: usize) -> Vec<Cell<'_>> {
+    let mut cells = vec![];
+    let mut x = 0;
+    for (position, (i, text)) in s.grapheme_indices(true).enumerate(){
+        let width = grapheme_width(text);
+        let ch = text.chars().next().unwrap();
+        cells.push(Cell{position, byte: offset + i, x, width, ch, text});
+        x += width;
+    }
+    cells
+}
 
-fn calc_dimension(s: &str) -> (usize, usize) {
-    let mut longest = 0;
-    for line in s.lines(){
-        let line_width = line.width();
-        if line_width > longest{
-            longest = line_width
+/// match the brackets using a stack of the opened brackets,
+/// a character only closes the innermost opened bracket if it is the close of that same pair
+fn match_brackets(cells: &[Cell], brackets: &[Bracket]) -> Vec<Span> {
+    let mut matched = vec![];
+    // the opened brackets together with the spans matched inside of each
+    let mut stack: Vec<(usize, Bracket, Vec<Span>)> = vec![];
+    for cell in cells{
+        let closing = match stack.last(){
+            Some(&(_, bracket, _)) => bracket.close == cell.ch,
+            None => false,
+        };
+        if closing{
+            let (open, bracket, spans) = stack.pop().unwrap();
+            let span = Span{open, close: cell.position, bracket, spans};
+            match stack.last_mut(){
+                Some(parent) => parent.2.push(span),
+                None => matched.push(span),
+            }
+        }
+        else if let Some(bracket) = brackets.iter().find(|b| b.open == cell.ch){
+            stack.push((cell.position, *bracket, vec![]));
         }
     }
-    let line_count = s.lines().count();
-    (longest, line_count)
+    // the spans inside an unclosed bracket are still matched
+    while let Some((_, _, spans)) = stack.pop(){
+        match stack.last_mut(){
+            Some(parent) => parent.2.extend(spans),
+            None => matched.extend(spans),
+        }
+    }
+    matched
 }
 
-/// return an SVG document base from the text infor string
-pub fn to_svg(s: &str, text_width: f32, text_height: f32) -> SVG {
-    let settings = &Settings{
-                text_width: text_width,
-                text_height: text_height,
-            };
-    let mut svg = SVG::new()
-            .set("font-size", 14)
-            .set("font-family", "arial");
-
-        svg.append(get_styles());
-    
-    let nodes = to_svg_lines(s,settings);
-    for elm in nodes{
-        let text_node = TextNode::new(elm.to_string());
-        svg.append(text_node);
+/// the outermost heads in this span,
+/// which is the span itself when its own face is a meme, otherwise the heads found inside of it.
+/// The face of the nested heads are excluded when testing the face of the enclosing span
+fn collect_heads(span: &Span, cells: &[Cell], options: &ParseOptions) -> Vec<Head> {
+    let heads: Vec<Head> = span.spans.iter()
+        .flat_map(|inner| collect_heads(inner, cells, options))
+        .collect();
+    let inside = &cells[span.open + 1..span.close];
+    // the marks combined with the open bracket are a part of the face
+    let open = &cells[span.open];
+    let marks = &open.text[open.ch.len_utf8()..];
+    let face: String = marks.chars().chain(inside.iter().flat_map(|c| c.text.chars())).collect();
+    let own_face: String = inside.iter()
+        .filter(|c| !heads.iter().any(|h| h.start_position <= c.position && c.position <= h.end_position))
+        .map(|c| c.text)
+        .collect();
+    if text_width(&face) <= options.max_face_width && score_meme(&own_face).is_meme(options.threshold){
+        vec![Head{
+            start_position: span.open,
+            span: byte_span(cells, span.open, span.close + 1),
+            startx: cells[span.open].x,
+            face,
+            face_span: open.byte + open.ch.len_utf8()..cells[span.close].byte,
+            endx: cells[span.close].x,
+            end_position: span.close,
+            bracket: Some(span.bracket),
+            close_grapheme: cells[span.close].text.to_string(),
+            heads,
+        }]
+    }else{
+        heads
     }
-
-    let (offsetx, offsety) = settings.offset();
-    let (wide, high) = calc_dimension(s);
-    let width = wide as f32 * text_width + offsetx;
-    let height = (high + 2 ) as f32 * text_height + offsety;
-    svg.assign("width", width);
-    svg.assign("height", height);
-    svg
 }
 
-
-fn get_styles() -> Style {
-    let style = r#"
-    line, path {
-      stroke: black;
-      stroke-width: 2;
-      stroke-opacity: 1;
-      fill-opacity: 1;
-      stroke-linecap: round;
-      stroke-linejoin: miter;
-    }
-    circle {
-      stroke: black;
-      stroke-width: 2;
-      stroke-opacity: 1;
-      fill-opacity: 1;
-      stroke-linecap: round;
-      stroke-linejoin: miter;
-      fill:white;
-    }
-    circle.donger{
-       stroke-width: 1;
-       fill: white;
-    }
-    tspan.head{
-        fill: none;
-        stroke: none;
-    }
-    "#;
-    Style::new(style)
+/// a head is opened by the open character of any of the bracket pairs
+/// and closed only by the close character of that same pair.
+/// The left side extends to the left until a space is encountered,
+/// the right side extends to the right up to and including a space
+fn parse_memes(s: &str, options: &ParseOptions) -> Body{
+    parse_line(s, 0, options)
 }
 
-/// process and parses each line
-fn to_svg_lines(s: &str, settings: &Settings) -> Vec<Box<Node>> {
-    let mut elements = vec![];
-    let mut y = 0;
-    for line in s.lines(){
-        let line_elm = get_svg_elements(y, line, settings);
-        elements.extend(line_elm);
-        y += 1;
-    }
-    elements
+/// parse the memes of the whole text, each line is parsed separately
+pub fn parse(s: &str) -> Document {
+    parse_with_options(s, &ParseOptions::default())
 }
 
-/// process only 1 line
-fn get_svg_elements(y: usize, s: &str, settings: &Settings) -> Vec<Box<Node>> {
-    let body = parse_memes(s);
-    body.get_svg_elements(y, &settings)
+/// parse the memes of the whole text using the options,
+/// only the faces that has a score of at least the threshold are memes
+pub fn parse_with_options(s: &str, options: &ParseOptions) -> Document {
+    let lines = s.lines()
+        .map(|line| {
+            let offset = line.as_ptr() as usize - s.as_ptr() as usize;
+            parse_line(line, offset, options)
+        })
+        .collect();
+    Document{lines}
 }
 
-/// return the SVG nodes per line and all the assembled rest of the string that is not a part of the memes
-pub fn get_meme_svg(input: &str, text_width: f32, text_height: f32) -> (Vec<Box<Node>>, String, Style) {
-    let settings = &Settings{
-                text_width: text_width,
-                text_height: text_height,
-            };
-    let mut svg_elements:Vec<Box<Node + 'static>> = vec![];
-    let mut relines = String::new();
-    let mut y = 0;
-    for line in input.lines(){
-        match  line_to_svg_with_excess_str(y, line, settings){
-            Some((svg_elm, rest_text)) => {
-                relines.push_str(&rest_text);
-                relines.push('\n');
-                svg_elements.extend(svg_elm);
-            },
-            None => {
-                relines.push_str(line);
-                relines.push('\n');
+/// parse a line which starts at the byte offset in the parsed text
+fn parse_line(s: &str, offset: usize, options: &ParseOptions) -> Body{
+    let cells = to_cells(s, offset);
+    let code = code_spans(&cells);
+    let in_code = |position: usize| code.iter().any(|c| c.contains(&position));
+    let heads: Vec<Head> = match_brackets(&cells, &options.brackets).iter()
+        .flat_map(|span| collect_heads(span, &cells, options))
+        .filter(|head| !in_code(head.start_position))
+        .collect();
+    let mut memes: Vec<Meme> = vec![];
+    for (i, head) in heads.iter().enumerate(){
+        let bound = match memes.last(){
+            Some(meme) => meme.end_position + 1,
+            None => 0,
+        };
+        let mut start = head.start_position;
+        let mut left_width = 0;
+        while start > bound && left_width + cells[start - 1].width <= options.max_arm_width
+            && !ends_arm(&cells, start - 1, start.checked_sub(2), options){
+            start -= 1;
+            left_width += cells[start].width;
+        }
+        // an arm doesn't start with the space it crossed
+        while start < head.start_position && cells[start].ch == ' '{
+            start += 1;
+        }
+        let limit = match heads.get(i + 1){
+            Some(next) => next.start_position,
+            None => cells.len(),
+        };
+        let mut end = head.end_position + 1;
+        let mut right_width = 0;
+        while end < limit && right_width + cells[end].width <= options.max_arm_width{
+            right_width += cells[end].width;
+            end += 1;
+            if ends_arm(&cells, end - 1, Some(end), options){
+                break;
             }
         }
-        y += 1;
-    } 
-    (svg_elements, relines, get_styles())
+        memes.push(Meme{
+            start_position: start,
+            span: byte_span(&cells, start, end),
+            head: head.clone(),
+            end_position: end - 1,
+            left_side: cells[start..head.start_position].iter().map(|c| c.text).collect(),
+            left_span: byte_span(&cells, start, head.start_position),
+            right_side: cells[head.end_position + 1..end].iter().map(|c| c.text).collect(),
+            right_span: byte_span(&cells, head.end_position + 1, end),
+        });
+    }
+    let bracketless = parse_bracketless_memes(&cells, &memes, options);
+    memes.extend(bracketless.into_iter().filter(|m| !in_code(m.head.start_position)));
+    memes.sort_by_key(|m| m.start_position);
+    Body{
+        rest_str: rest_text(&cells, &memes),
+        memes,
+        offset,
+    }
 }
 
-/// parse the memes and return the svg together with the unmatched strings
-fn line_to_svg_with_excess_str(y: usize, s: &str, settings:&Settings) -> Option<(Vec<Box<Node>>, String)>{
-    let body = parse_memes(s);
-    if body.has_memes(){
-        let nodes = body.get_svg_elements(y, settings);
-        Some((nodes, body.unify_rest_text()))
-    }else{
-        None
+/// whether the cell at this position ends an arm, the cell beyond it is on the far side of the arm.
+/// Only a space ends an arm, unless the arm crosses a space that is followed by more of the arm
+fn ends_arm(cells: &[Cell], position: usize, beyond: Option<usize>, options: &ParseOptions) -> bool {
+    if cells[position].ch != ' '{
+        return false;
+    }
+    if !options.arms_cross_spaces{
+        return true;
+    }
+    match beyond.and_then(|b| cells.get(b)){
+        Some(cell) => cell.ch == ' ' || cell.ch.is_ascii_alphanumeric(),
+        None => true,
     }
 }
 
-#[test]
-fn test_1line(){
-    let meme = "";
-    let nodes = get_svg_elements(0, meme, &Settings::default());
-    assert_eq!(nodes.len(), 0);
+/// the positions enclosed in a pair of backticks, which are code spans in markdown.
+/// The opening backtick starts a word and the closing backtick ends a word,
+/// so the backticks used inside faces such as ¯`·.·´¯ don't start a code span
+fn code_spans(cells: &[Cell]) -> Vec<Range<usize>> {
+    let mut spans = vec![];
+    let mut opened = None;
+    for cell in cells.iter().filter(|c| c.ch == '`'){
+        let before = cell.position.checked_sub(1).map(|p| cells[p].ch);
+        let after = cells.get(cell.position + 1).map(|c| c.ch);
+        match opened{
+            Some(open) if after.is_none_or(|c| c.is_whitespace() || c.is_ascii_punctuation()) => {
+                spans.push(open..cell.position + 1);
+                opened = None;
+            }
+            None if before.is_none_or(|c| c.is_whitespace()) => opened = Some(cell.position),
+            _ => (),
+        }
+    }
+    spans
 }
 
-
-/// TODO: include parsing the rest of the unused text
-fn parse_memes(s: &str) -> Body{
-    let mut memes = vec![];
-    let mut paren_opened = false;
-    let mut meme_face = String::new();
-    let mut index = 0;
-    let mut total_width = 0;
-    let mut face_markers:Vec<Head> = vec![];
-    let mut startx = 0;
-    let mut start_position = 0;
-    let mut meme_start = 0;
-    let mut meme_body = String::new();
-    let mut meme_left_side = String::new();
-    let mut meme_right_side = String::new();
-    let mut meme_head = None;
-    let total_chars = s.chars().count();
-    let mut rest_text:Vec<(usize, String)> = vec![];
-    for ch in s.chars(){
-        let last_char = index == total_chars - 1;
-        if meme_head.is_some(){
-            meme_right_side.push(ch);
-        }
-        if paren_opened && ch == ')'{ //if paren_opened and encountered a closing
-            paren_opened  = false;
-            if is_meme(&meme_face){
+/// the punctuation that may follow a face without brackets
+const TRAILING_PUNCTUATION: [char; 8] = [',', '.', ';', ':', '!', '?', '"', '\''];
+
+/// the second pass, which detects the faces that have no brackets
+/// from the words that are not yet part of a meme.
+/// The whole word is the face and the head is synthesized around it
+fn parse_bracketless_memes(cells: &[Cell], memes: &[Meme], options: &ParseOptions) -> Vec<Meme> {
+    let mut found = vec![];
+    for word in cells.split(|c| c.ch == ' '){
+        // the punctuation that ends a sentence or a clause is left as rest of the text
+        let punctuation = word.iter().rev().take_while(|c| TRAILING_PUNCTUATION.contains(&c.ch)).count();
+        let word = &word[..word.len() - punctuation];
+        if let (Some(first), Some(last)) = (word.first(), word.last()){
+            let in_meme = memes.iter()
+                .any(|m| m.start_position <= last.position && first.position <= m.end_position);
+            let face: String = word.iter().map(|c| c.text).collect();
+            if !in_meme && is_bracketless_meme(&face, options){
+                // include the space that ends the word, just like the right side of memes with brackets
+                let end = match cells.get(last.position + 1){
+                    Some(next) if punctuation == 0 => next.position,
+                    _ => last.position,
+                };
                 let head = Head{
-                    start_position: start_position,
-                    startx: startx,
-                    face: meme_face.clone(),
-                    end_position: index,
-                    endx: total_width,
+                    start_position: first.position,
+                    span: byte_span(cells, first.position, last.position + 1),
+                    startx: first.x,
+                    face,
+                    face_span: byte_span(cells, first.position, last.position + 1),
+                    endx: last.x + last.width,
+                    end_position: last.position,
+                    bracket: None,
+                    close_grapheme: String::new(),
+                    heads: vec![],
                 };
-                meme_head = Some(head.clone());
-                face_markers.push(head);
-                meme_face.clear();
+                found.push(Meme{
+                    start_position: first.position,
+                    span: byte_span(cells, first.position, end + 1),
+                    head,
+                    end_position: end,
+                    left_side: String::new(),
+                    left_span: byte_span(cells, first.position, first.position),
+                    right_side: cells[last.position + 1..end + 1].iter().map(|c| c.text).collect(),
+                    right_span: byte_span(cells, last.position + 1, end + 1),
+                });
             }
         }
-        if paren_opened{
-           meme_face.push(ch); 
-        }
-        if ch == '('{
-            paren_opened = true;
-            startx = total_width;
-            start_position = index;
-            meme_left_side = meme_body.clone();
-            meme_face.clear();
-        }
-
-        if meme_head.is_none() && (ch == ' ' || last_char){
-            meme_start = index + 1;
-            if !paren_opened{
-                let mut rest_word = meme_body.clone();
-                let rest_start = total_width - rest_word.width();
-                if last_char{
-                   rest_word.push(ch);
-                   rest_word.push_str(&meme_face);//the head is unmatched
-                }
-                rest_text.push((rest_start, rest_word));
-            }
-            meme_body.clear();
-        }
-        if meme_head.is_some() && (ch == ' ' || last_char){
-            let meme = Meme{
-               start_position: meme_start,  
-               head: meme_head.clone().unwrap(),
-               end_position: index,
-               left_side: meme_left_side.clone(), 
-               right_side: meme_right_side.clone(),
-            };
-            memes.push(meme);
-            meme_right_side.clear();
-            meme_left_side.clear();
-            meme_body.clear();
-            meme_head = None;
-        }
-        meme_body.push(ch);
-        if let Some(uw) = ch.width(){
-            total_width += uw;
-        }
-        index += 1;
-    } 
-    Body{
-        memes: memes,
-        rest_str: regroup_rest_text(&rest_text)
     }
+    found
 }
 
-fn regroup_rest_text(rest_text: &Vec<(usize, String)>)->Vec<(usize, String)>{
-    let mut new_group = vec![];
-    //println!("regrouping text..");
-    for &(start,ref rest) in rest_text{
-        if new_group.is_empty(){
-            new_group.push((start, rest.clone()));
-        }else{
-            if let Some((lastx, last_rest)) = new_group.pop(){
-               if lastx + last_rest.width() == start{
-                    let mut merged = String::new();
-                    merged.push_str(&last_rest);
-                    merged.push_str(rest);
-                    new_group.push((lastx, merged));
-               }else{
-                   new_group.push((lastx, last_rest));
-                   new_group.push((start, rest.clone()));
-               } 
-            }
+/// the byte range in the parsed text of the characters
+/// from the start position up to but not including the end position
+fn byte_span(cells: &[Cell], start: usize, end: usize) -> Range<usize> {
+    let byte_at = |position: usize| match cells.get(position){
+        Some(cell) => cell.byte,
+        None => cells.last().map(|c| c.byte + c.text.len()).unwrap_or(0),
+    };
+    byte_at(start)..byte_at(end)
+}
+
+/// group the characters that are not part of any meme
+fn rest_text(cells: &[Cell], memes: &[Meme]) -> Vec<(usize, String)> {
+    let mut rest: Vec<(usize, String)> = vec![];
+    let mut last_position = None;
+    for cell in cells{
+        let in_meme = memes.iter()
+            .any(|m| m.start_position <= cell.position && cell.position <= m.end_position);
+        if in_meme{
+            continue;
+        }
+        let contiguous = cell.position > 0 && last_position == Some(cell.position - 1);
+        match rest.last_mut(){
+            Some(&mut (_, ref mut word)) if contiguous => word.push_str(cell.text),
+            _ => rest.push((cell.x, cell.text.to_string())),
         }
+        last_position = Some(cell.position);
     }
-    //println!("new_group: {:#?}", new_group);
-    new_group
+    rest
 }
 
 #[test]
@@ -533,7 +2442,7 @@ fn test_expression(){
 #[test]
 fn test_bound(){
     let meme = "(♥_♥)";
-    let memes = parse_memes(meme); 
+    let memes = parse_memes(meme, &ParseOptions::default()); 
     for m in memes.memes{
         let b = m.head;
         println!("bound {:?} d:{} ", b , b.distance());
@@ -560,7 +2469,7 @@ fn escape_char(ch: &char) -> String {
         Some(&(_, quoted)) => String::from(quoted),
         None => {
             let mut s = String::new();
-            s.push(*ch);
+            s.push(to_xml_char(*ch));
             s
         }
     };
@@ -568,6 +2477,16 @@ fn escape_char(ch: &char) -> String {
 
 }
 
+/// the control characters that are not allowed in xml are replaced
+fn to_xml_char(ch: char) -> char {
+    if (ch < ' ' && ch != '\t' && ch != '\n' && ch != '\r') || ch == '\u{FFFE}' || ch == '\u{FFFF}'{
+        '\u{FFFD}'
+    }
+    else{
+        ch
+    }
+}
+
 
 fn is_operator(c: char) -> bool{
     c == '+' || c == '-' || c == '*' || c == '/'
@@ -585,13 +2504,109 @@ fn test_operator(){
     assert!(is_expression("x^2 * y^2"));
 }
 
+/// recognize the technical text, which is never a meme even if it scores like one
+fn recognize_technical(ch: &str) -> Option<Technical> {
+    let s = ch.trim();
+    if is_url(s){
+        Some(Technical::Url)
+    }else if is_email(s){
+        Some(Technical::Email)
+    }else if is_latex(s){
+        Some(Technical::Latex)
+    }else if is_greek_math(s){
+        Some(Technical::Math)
+    }else if is_identifier(s) || is_pattern(s){
+        Some(Technical::Identifier)
+    }else{
+        None
+    }
+}
+
+/// a scheme followed by :// such as https://x.y/ä, or an address starting with www.
+fn is_url(s: &str) -> bool {
+    if s.is_empty() || s.chars().any(|c| c.is_whitespace()){
+        return false;
+    }
+    match s.find("://"){
+        Some(i) => {
+            let scheme = &s[..i];
+            i + 3 < s.len()
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '.' || c == '-')
+        }
+        None => s.starts_with("www.") && s.len() > 4,
+    }
+}
+
+/// a local part and a domain with a dot, separated by @
+fn is_email(s: &str) -> bool {
+    let parts: Vec<&str> = s.split('@').collect();
+    if parts.len() != 2{
+        return false;
+    }
+    let (local, domain) = (parts[0], parts[1]);
+    !local.is_empty()
+    && local.chars().all(|c| c.is_alphanumeric() || "._%+-".contains(c))
+    && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.')
+    && domain.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-')
+}
+
+/// a latex command such as \alpha or \frac{a}{b}, a braced super or subscript such as x^{2},
+/// or anything enclosed in $
+fn is_latex(s: &str) -> bool {
+    let chars: Vec<char> = s.chars().collect();
+    let command = chars.windows(3)
+        .any(|w| w[0] == '\\' && w[1].is_ascii_alphabetic() && w[2].is_ascii_alphabetic());
+    let braced = s.contains("^{") || s.contains("_{");
+    // the math in between the dollars has letters, digits or operators, unlike faces such as $_$
+    let dollars = chars.len() > 2 && s.starts_with('$') && s.ends_with('$')
+        && chars[1..chars.len() - 1].iter().any(|&c| c.is_ascii_alphanumeric() || "+-*/=<>^\\".contains(c));
+    command || braced || dollars
+}
+
+/// greek letters used together with operators, math symbols or ascii letters and digits.
+/// Symmetric text such as ^ω^ is a face rather than math
+fn is_greek_math(s: &str) -> bool {
+    let is_greek = |c: char| ('\u{370}'..='\u{3FF}').contains(&c);
+    let is_math_symbol = |c: char| ('\u{2200}'..='\u{22FF}').contains(&c)
+        || ('\u{2070}'..='\u{209F}').contains(&c)
+        || "²³¹×÷·′".contains(c);
+    let is_math = |c: char| is_operator(c) || is_math_symbol(c) || "=<>()[]{}'".contains(c);
+    s.chars().any(is_greek)
+    && s.chars().all(|c| is_greek(c) || c.is_ascii_alphanumeric() || c == ' ' || is_math(c))
+    && s.chars().any(|c| c.is_ascii_alphanumeric() || is_math(c))
+    && !is_symmetric(s)
+}
+
+/// identifiers and paths in ascii such as foo_bar, foo::bar, self.x or src/lib.rs,
+/// with at least 2 letters in a row so faces like x_x or o.O are not identifiers.
+/// Symmetric text without any separator such as UwU is a face rather than an identifier
+fn is_identifier(s: &str) -> bool {
+    let is_separator = |c: char| c == '_' || ":./\\-#~".contains(c);
+    let chars: Vec<char> = s.chars().collect();
+    let letters = chars.windows(2)
+        .any(|w| w[0].is_ascii_alphabetic() && w[1].is_ascii_alphabetic());
+    letters
+    && chars.iter().all(|&c| c.is_ascii_alphanumeric() || is_separator(c))
+    && (chars.iter().any(|&c| is_separator(c)) || !is_symmetric(s))
+}
+
+/// the placeholders and identifiers separated by commas, as in the patterns Some(_) or (_, b),
+/// with at least 1 lone underscore. The underscore as a mouth such as ^_^ or x_x is not a placeholder
+fn is_pattern(s: &str) -> bool {
+    let is_name = |p: &str| p.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && p.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let parts: Vec<&str> = s.split(',').map(|p| p.trim()).collect();
+    parts.contains(&"_") && parts.iter().all(|p| is_name(p))
+}
+
 //TODO: alternate alphanumeric_space and operator
 fn is_expression(ch: &str) -> bool{
     is_alphanumeric_space_operator(ch) 
 }
 
 fn is_alphanumeric_space_operator(ch:&str) -> bool{
-    ch.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '_' || is_operator(c))
+    ch.chars().all(|c| c.is_alphanumeric() || c == ' ' || is_operator(c))
 }
 
 
@@ -599,7 +2614,7 @@ fn is_alphanumeric_space_operator(ch:&str) -> bool{
 fn test_body(){
     let meme = "( ^_^)ノ";
     println!("{}", meme);
-    let bodies = parse_memes(meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
     for b in &bodies.memes{
         println!("{:#?}",b);
     }
@@ -610,7 +2625,7 @@ fn test_body(){
 fn test_body2(){
     let meme = "ヘ( ^_^)ノ ＼(^_^ )Gimme Five";
     println!("{}", meme);
-    let bodies = parse_memes(meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
     for b in &bodies.memes{
         println!("{:#?}",b);
     }
@@ -621,7 +2636,7 @@ fn test_body2(){
 fn test_rest_of_text(){
     let meme = r#"The rest of   凸(•̀_•́)凸❤️ ( ͡° ͜ʖ ͡°) \(°□°)/层∀  the text is here"#;
     println!("{}", meme);
-    let bodies = parse_memes(meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
     println!("{:#?}",bodies);
     assert_eq!(3, bodies.memes.len());
     assert_eq!(2, bodies.rest_str.len());
@@ -630,14 +2645,14 @@ fn test_rest_of_text(){
 #[test]
 fn test_unify_rest_of_text(){
     let meme = r#"The rest of   凸(•̀_•́)凸❤️ ( ͡° ͜ʖ ͡°) \(°□°)/层∀  the text is here"#;
-    let resi = r#"The rest of                                   the text is here"#;
+    let resi = r#"The rest of                                    the text is here"#;
     println!("{}", meme);
-    let bodies = parse_memes(meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
     println!("{:#?}",bodies);
     assert_eq!(3, bodies.memes.len());
     assert_eq!(2, bodies.rest_str.len());
-    assert_eq!(meme.width(), bodies.unify_rest_text().width());
-    println!("residue: {} meme: {} rest_text:{}", resi.width(), meme.width(), bodies.unify_rest_text().width());
+    assert_eq!(text_width(meme), text_width(&bodies.unify_rest_text()));
+    println!("residue: {} meme: {} rest_text:{}", text_width(resi), text_width(meme), text_width(&bodies.unify_rest_text()));
     assert_eq!(resi.to_string(), bodies.unify_rest_text());
 }
 
@@ -645,7 +2660,7 @@ fn test_unify_rest_of_text(){
 fn test_meme_equation(){
     let meme= r#"Equations are not rendered? ( -_- )  __(x+y)__  (^_^) (x^2+y^2)x"#;
     println!("{}", meme);
-    let bodies = parse_memes(meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
     println!("{:#?}",bodies);
     assert_eq!(2, bodies.memes.len());
     assert_eq!(3, bodies.rest_str.len());
@@ -655,7 +2670,7 @@ fn test_meme_equation(){
 fn test_meme_equation2(){
     let meme= r#"( -_- ) __(x+y)__ (^_^) (x^2+y^2)"#;
     println!("{}", meme);
-    let bodies = parse_memes(meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
     println!("{:#?}",bodies);
     assert_eq!(2, bodies.memes.len());
     assert_eq!(2, bodies.rest_str.len());
@@ -666,10 +2681,471 @@ fn test_meme_equation2(){
 fn test_meme_unmatched_face(){
     let meme= r#"(╯°□°] ╯︵ ┬─┻"#;
     println!("{}", meme);
-    let bodies = parse_memes(meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
+    println!("{:#?}",bodies);
+    assert_eq!(0, bodies.memes.len());
+    assert_eq!(1, bodies.rest_str.len());
+    assert_eq!(meme.to_string(), bodies.unify_rest_text());
+}
+
+
+
+#[test]
+fn test_meme_bracket_pairs(){
+    let meme= r#"⎝༼ຈل͜ຈ༽⎠ ༼⊙ʖ̯⊙༽ ʕ•ᴥ•ʔ （╯°□°）╯︵ ┻━┻ 【ツ】"#;
+    println!("{}", meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
+    println!("{:#?}",bodies);
+    assert_eq!(5, bodies.memes.len());
+    assert_eq!(Some(BRACKETS[2]), bodies.memes[0].head.bracket);
+    assert_eq!("⎝", bodies.memes[0].left_side);
+    assert_eq!("ຈل͜ຈ", bodies.memes[0].head.face);
+    assert_eq!(Some(BRACKETS[4]), bodies.memes[2].head.bracket);
+    assert_eq!(Some(BRACKETS[1]), bodies.memes[3].head.bracket);
+    assert_eq!("╯︵", bodies.memes[3].right_side.trim());
+    assert_eq!(Some(BRACKETS[3]), bodies.memes[4].head.bracket);
+}
+
+#[test]
+fn test_meme_mismatched_bracket_pair(){
+    let meme= r#"(╯°□°） ╯︵ ┻━┻"#;
+    println!("{}", meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
     println!("{:#?}",bodies);
     assert_eq!(0, bodies.memes.len());
+}
+
+#[test]
+fn test_meme_custom_brackets(){
+    let meme= r#"(ツ) [ツ]"#;
+    let square = Bracket{open: '[', close: ']'};
+    let options = ParseOptions{brackets: vec![square], ..ParseOptions::default()};
+    let bodies = parse_memes(meme, &options);
+    assert_eq!(1, bodies.memes.len());
+    assert_eq!(Some(square), bodies.memes[0].head.bracket);
+}
+
+#[test]
+fn test_marks_on_brackets(){
+    let memes = [
+        ("٩(͡๏̯͡๏)۶", "͡๏̯͡๏"),
+        ("（͡°͜ʖ͡°）", "͡°͜ʖ͡°"),
+        ("ლ(́◉◞౪◟◉‵ლ)", "́◉◞౪◟◉‵ლ"),
+        ("(ツ)̀ yes", "ツ"),
+    ];
+    for &(meme, face) in memes.iter(){
+        let bodies = parse_memes(meme, &ParseOptions::default());
+        assert_eq!(1, bodies.memes.len(), "{}", meme);
+        let head = &bodies.memes[0].head;
+        assert_eq!(face, head.face);
+        assert_eq!(face, &meme[head.face_span.clone()]);
+        assert_eq!(meme.split(' ').next().unwrap(), bodies.memes[0].text().trim());
+        // the marks are drawn while the brackets are hidden
+        for &grid_fit in [GridFit::Off, GridFit::Glyph].iter(){
+            let svg = to_svg(meme, &RenderOptions::new().grid_fit(grid_fit)).to_string();
+            let drawn: String = face.chars().filter(|c| !c.is_whitespace()).collect();
+            let document = roxmltree::Document::parse(&svg).unwrap();
+            let text: String = document.descendants()
+                .filter(|node| node.attribute("class") == Some("face"))
+                .flat_map(|node| node.descendants())
+                .filter(|node| node.is_text() && node.parent().and_then(|p| p.attribute("class")) != Some("head"))
+                .filter_map(|node| node.text())
+                .flat_map(|text| text.chars())
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            assert!(text.starts_with(&drawn), "{} {}", text, drawn);
+        }
+    }
+}
+
+#[test]
+fn test_nested_heads(){
+    let meme = "( ͡° ͜ʖ ( ͡° ͜ʖ ( ͡° ͜ʖ ͡°) ͜ʖ ͡°)ʖ ͡°)";
+    println!("{}", meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
+    println!("{:#?}",bodies);
+    assert_eq!(1, bodies.memes.len());
+    let outer = &bodies.memes[0].head;
+    assert_eq!(text_width(meme), outer.distance() + 1);
+    assert_eq!(1, outer.heads.len());
+    let middle = &outer.heads[0];
+    assert_eq!(1, middle.heads.len());
+    let inner = &middle.heads[0];
+    assert_eq!(" ͡° ͜ʖ ͡°", inner.face);
+    assert!(inner.heads.is_empty());
+    let face: String = glyph_content(&outer.get_glyphs(), &RenderOptions::default()).iter()
+        .map(|content| content.to_string())
+        .collect();
+    assert_eq!(meme.replace("(", r#"<tspan class="head">(</tspan>"#).replace(")", r#"<tspan class="head">)</tspan>"#), face);
+    assert_eq!(4, outer.get_svg_elements(&Row{index: 0, top: 0.0, outlines: vec![]}, &RenderOptions::default()).len());
+}
+
+#[test]
+fn test_nested_heads_in_text(){
+    let meme = "(see ( ͡° ͜ʖ ͡°) here) ༼༼;; ;°;ਊ°;༽";
+    println!("{}", meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
+    println!("{:#?}",bodies);
+    assert_eq!(2, bodies.memes.len());
+    assert_eq!(" ͡° ͜ʖ ͡°", bodies.memes[0].head.face);
+    assert_eq!(" ", bodies.memes[0].right_side);
+    assert_eq!("(see ", bodies.rest_str[0].1);
+    assert_eq!(";; ;°;ਊ°;", bodies.memes[1].head.face);
+    assert_eq!("༼", bodies.memes[1].left_side);
+}
+
+#[test]
+fn test_bracketless_meme(){
+    let options = ParseOptions::default();
+    assert!(is_bracketless_meme("ಠ_ಠ", &options));
+    assert!(is_bracketless_meme("ಥ_ಥ", &options));
+    assert!(is_bracketless_meme("ಠ益ಠ", &options));
+    assert!(is_bracketless_meme("◕‿◕", &options));
+    assert!(is_bracketless_meme("ツ", &options));
+    assert!(!is_bracketless_meme("-_-", &options));
+    assert!(!is_bracketless_meme("|_|", &options));
+    assert!(!is_bracketless_meme("/_/", &options));
+    assert!(!is_bracketless_meme("\\_\\", &options));
+    assert!(!is_bracketless_meme("#_#", &options));
+    assert!(is_bracketless_meme("^˚ᴥ˚^", &options));
+    assert!(!is_bracketless_meme("snake_case", &options));
+    assert!(!is_bracketless_meme("a.a", &options));
+    assert!(!is_bracketless_meme("---", &options));
+    assert!(!is_bracketless_meme("┻━┻", &options));
+    assert!(!is_bracketless_meme("┬─┬", &options));
+    assert!(!is_bracketless_meme("=>", &options));
+    assert!(!is_bracketless_meme("ツツ", &options));
+    assert!(!is_bracketless_meme("ラーメン", &options));
+}
+
+#[test]
+fn test_bracketless_memes_in_text(){
+    let meme = "meme_disapp => Look of disapproval => ಠ_ಠ (ツ) ツ ( ಥ_ಥ )";
+    println!("{}", meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
+    println!("{:#?}",bodies);
+    assert_eq!(4, bodies.memes.len());
+    let disapproval = &bodies.memes[0].head;
+    assert_eq!("ಠ_ಠ", disapproval.face);
+    assert_eq!(None, disapproval.bracket);
+    assert_eq!(3, disapproval.distance());
+    assert_eq!(vec![Content::Text("ಠ_ಠ".to_string())], glyph_content(&disapproval.get_glyphs(), &RenderOptions::default()));
+    assert_eq!(Some(BRACKETS[0]), bodies.memes[1].head.bracket);
+    assert_eq!(None, bodies.memes[2].head.bracket);
+    assert_eq!(" ಥ_ಥ ", bodies.memes[3].head.face);
     assert_eq!(1, bodies.rest_str.len());
+
+    let meme = "Really ಠ_ಠ, stop it ツ. And |_| /_/ #_#";
+    let bodies = parse_memes(meme, &ParseOptions::default());
+    assert_eq!(2, bodies.memes.len());
+    assert_eq!("ಠ_ಠ", bodies.memes[0].head.face);
+    assert_eq!("ಠ_ಠ", &meme[bodies.memes[0].span.clone()]);
+    assert_eq!("ツ", bodies.memes[1].head.face);
+    assert_eq!(", stop it ", bodies.rest_str[1].1);
+    assert_eq!(". And |_| /_/ #_#", bodies.rest_str[2].1);
+}
+
+#[test]
+fn test_parse(){
+    let text = "No memes here\nand ヘ( ^_^)ノ ＼(^_^ )Gimme Five\r\nಠ_ಠ";
+    let doc = parse(text);
+    println!("{:#?}", doc);
+    assert_eq!(3, doc.lines.len());
+    assert!(!doc.lines[0].has_memes());
+    let memes = &doc.lines[1].memes;
+    assert_eq!(2, memes.len());
+    assert_eq!(4, memes[0].start_position);
+    assert_eq!(5, memes[0].head.start_position);
+    assert_eq!(6, memes[0].head.startx);
+    assert_eq!(text.find("ヘ( ^_^)").unwrap(), memes[0].span.start);
+    assert_eq!(text.find("( ^_^)").unwrap(), memes[0].head.span.start);
+    assert_eq!(text.find("(^_^ )").unwrap(), memes[1].head.span.start);
+    assert_eq!("ヘ", memes[0].left_side);
+    assert_eq!("Gimme", memes[1].right_side.trim());
+    assert_eq!(text.find("ಠ_ಠ").unwrap(), doc.lines[2].memes[0].head.span.start);
+}
+
+#[test]
+fn test_spans(){
+    let text = "The rest of   凸(•̀_•́)凸❤️ ( ͡° ͜ʖ ( ͡° ͜ʖ ͡°) ͡°) \\(°□°)/层∀  the text\nಠ_ಠ is here （╯°□°）╯︵ ┻━┻";
+    let doc = parse(text);
+    println!("{:#?}", doc);
+    let mut count = 0;
+    for body in &doc.lines{
+        for meme in &body.memes{
+            let head = &meme.head;
+            assert_eq!(meme.left_side, &text[meme.left_span.clone()]);
+            assert_eq!(meme.right_side, &text[meme.right_span.clone()]);
+            assert_eq!(head.face, &text[head.face_span.clone()]);
+            assert_eq!(meme.left_span.start, meme.span.start);
+            assert_eq!(meme.left_span.end, head.span.start);
+            assert_eq!(head.span.end, meme.right_span.start);
+            assert_eq!(meme.right_span.end, meme.span.end);
+            let mut whole = meme.left_side.clone();
+            if let Some(bracket) = head.bracket{
+                whole.push(bracket.open);
+                whole.push_str(&head.face);
+                whole.push(bracket.close);
+                assert_eq!(head.face_span.start, head.span.start + bracket.open.len_utf8());
+            }else{
+                whole.push_str(&head.face);
+            }
+            whole.push_str(&meme.right_side);
+            assert_eq!(whole, &text[meme.span.clone()]);
+            for nested in &head.heads{
+                assert_eq!(nested.face, &text[nested.face_span.clone()]);
+            }
+            count += 1;
+        }
+    }
+    assert_eq!(5, count);
+}
+
+#[test]
+fn test_grapheme_width(){
+    assert_eq!(1, text_width("•̀"));
+    assert_eq!(2, text_width("💪🏾"));
+    assert_eq!(2, text_width("❤️"));
+    assert_eq!(2, text_width("☝️🏻"));
+    assert_eq!(2, text_width("👨‍👩‍👧‍👦"));
+    assert_eq!(2, text_width("👩‍❤️‍💋‍👩"));
+    assert_eq!(2, text_width("🇯🇵"));
+    assert_eq!(2, text_width("0️⃣"));
+    assert_eq!(9, text_width("凸(•̀_•́)凸"));
+    assert_eq!(8, text_width("( ͡° ͜ʖ ͡°)"));
+}
+
+#[test]
+fn test_grapheme_positions(){
+    let meme = "💪🏾(ò_óˇ)ᕤ 👨‍👩‍👧 ( •̀_•́ ) 🇯🇵 (ツ)";
+    println!("{}", meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
+    println!("{:#?}",bodies);
+    assert_eq!(3, bodies.memes.len());
+    let flex = &bodies.memes[0];
+    assert_eq!("💪🏾", flex.left_side);
+    assert_eq!(1, flex.head.start_position);
+    assert_eq!(2, flex.head.startx);
+    assert_eq!(7, flex.head.endx);
+    let wink = &bodies.memes[1].head;
+    assert_eq!(13, wink.startx);
+    assert_eq!(6, wink.distance());
+    assert_eq!(" •̀_•́ ", wink.face);
+    let happy = &bodies.memes[2].head;
+    assert_eq!(24, happy.startx);
+    assert_eq!((10, "👨‍👩‍👧 ".to_string()), bodies.rest_str[0]);
+    assert_eq!((21, "🇯🇵 ".to_string()), bodies.rest_str[1]);
+}
+
+#[test]
+fn test_meme_score(){
+    let lenny = score_meme(" ͡° ͜ʖ ͡°");
+    println!("{:#?}", lenny);
+    assert_eq!(0.5, lenny.non_ascii);
+    assert_eq!(1.0, lenny.zero_width);
+    assert_eq!(0.5, lenny.symmetry);
+    assert_eq!(1.0, lenny.expression);
+    assert!(lenny.is_meme(MEME_THRESHOLD));
+    assert!(!lenny.is_meme(5.0));
+
+    let equation = score_meme("x^2+y^2");
+    assert_eq!(-1.0, equation.expression);
+    assert!(!equation.is_meme(MEME_THRESHOLD));
+    assert!(equation.is_meme(-1.0));
+
+    let tableflip = score_meme("╯°□°");
+    assert_eq!(1.0, tableflip.high_codepoint);
+    assert_eq!(0.0, tableflip.symmetry);
+    assert!(is_symmetric("ò_ó"));
+    assert!(!is_symmetric("o_"));
+}
+
+#[test]
+fn test_meme_score_long_face(){
+    // the width is a penalty rather than a hard limit
+    let long = "ﾉ ͡° ͜ʖ ͡° ͡° ͜ʖ ͡°ﾉ";
+    assert!(text_width(long) > WIDE_FACE_WIDTH);
+    let score = score_meme(long);
+    println!("{:#?}", score);
+    assert!(score.width < 0.0);
+    assert!(score.is_meme(MEME_THRESHOLD));
+    assert!(!is_meme("this is a rather long aside"));
+}
+
+#[test]
+fn test_parse_with_threshold(){
+    let text = "(ツ) (ಠ_ಠ) (x+y)";
+    assert_eq!(2, parse(text).lines[0].memes.len());
+    let strict = ParseOptions{threshold: 2.0, ..ParseOptions::default()};
+    assert_eq!(1, parse_with_options(text, &strict).lines[0].memes.len());
+    let lenient = ParseOptions{threshold: -1.0, ..ParseOptions::default()};
+    assert_eq!(3, parse_with_options(text, &lenient).lines[0].memes.len());
+}
+
+#[test]
+fn test_parse_max_face_width(){
+    let text = "(ノಠ益ಠ)ノ";
+    assert_eq!(1, parse(text).lines[0].memes.len());
+    let narrow = ParseOptions{max_face_width: 1, ..ParseOptions::default()};
+    assert_eq!(0, parse_with_options(text, &narrow).lines[0].memes.len());
+    // the faces that score high are still cut at the width
+    let text = "( ͡° ͜ʖ ͡°) (╯°□°)╯ (ツ) ಠ_ಠ";
+    let faces = |options: &ParseOptions| -> Vec<String> {
+        parse_with_options(text, options).lines[0].memes.iter().map(|m| m.head.face.clone()).collect()
+    };
+    assert_eq!(vec![" ͡° ͜ʖ ͡°", "╯°□°", "ツ", "ಠ_ಠ"], faces(&ParseOptions::default()));
+    assert!(score_meme(" ͡° ͜ʖ ͡°").total() > 2.0);
+    assert_eq!(vec!["ツ", "ಠ_ಠ"], faces(&ParseOptions{max_face_width: 3, ..ParseOptions::default()}));
+}
+
+#[test]
+fn test_parse_max_arm_width(){
+    let text = "ヽ༼ຈل͜ຈ༽ﾉ~~~~~~";
+    let body = parse_memes(text, &ParseOptions::default());
+    assert_eq!("ﾉ~~~~~~", body.memes[0].right_side);
+    let short = ParseOptions{max_arm_width: 3, ..ParseOptions::default()};
+    let body = parse_memes(text, &short);
+    assert_eq!("ヽ", body.memes[0].left_side);
+    assert_eq!("ﾉ~~", body.memes[0].right_side);
+    assert_eq!("~~~~", body.rest_str[0].1);
+    let long = "(╯°□°)╯︵━━━━━━━━━━━━━━━━━━━━━━━━━━━━┻";
+    let body = parse_memes(long, &ParseOptions::default());
+    assert_eq!("╯︵━━━━━━━━━━━━━━━━━━━━━━━━━━━━┻", body.memes[0].right_side);
+}
+
+#[test]
+fn test_parse_arms_cross_spaces(){
+    let text = "flip (╯°□°)╯︵ ┻━┻ the table";
+    let body = parse_memes(text, &ParseOptions::default());
+    assert_eq!("╯︵ ", body.memes[0].right_side);
+    let crossing = ParseOptions{arms_cross_spaces: true, ..ParseOptions::default()};
+    let body = parse_memes(text, &crossing);
+    assert_eq!("", body.memes[0].left_side);
+    assert_eq!("╯︵ ┻━┻ ", body.memes[0].right_side);
+    assert_eq!("the table", body.rest_str[1].1);
+}
+
+#[test]
+fn test_prose(){
+    assert!(is_prose("日本語"));
+    assert!(is_prose("これは日本語です"));
+    assert!(is_prose("注：这是中文"));
+    assert!(is_prose("안녕하세요"));
+    assert!(is_prose("привет мир"));
+    assert!(is_prose("αβγ"));
+    assert!(is_prose("καλημέρα"));
+    assert!(!is_prose("ツ"));
+    assert!(!is_prose("ΘεΘ"));
+    assert!(!is_prose("ﾟДﾟ"));
+    assert!(!is_prose("ーー;"));
+    assert!(!is_prose("ㅇㅅㅇ"));
+    assert!(!is_prose("눈_눈"));
+    assert!(!is_prose("ᵔᴥᵔ"));
+    assert!(!is_prose("ﾉ◕ヮ◕"));
+}
+
+#[test]
+fn test_prose_in_brackets(){
+    let asides = [
+        "See the notes (日本語) for details",
+        "Sushi (寿司) is served (すし)",
+        "Hello (你好) world",
+        "Greetings (안녕하세요) everyone",
+        "Hello (привет) world",
+        "Morning (καλημέρα) everyone",
+        "ソニー(株)は",
+        "x (注) y",
+        "(Ж)",
+        "(한)",
+    ];
+    for aside in asides.iter(){
+        let bodies = parse_memes(aside, &ParseOptions::default());
+        println!("{:#?}", bodies);
+        assert_eq!(0, bodies.memes.len(), "{}", aside);
+    }
+    let faces = "(ツ) (ΘεΘ;) (ﾟДﾟ) (ㅇㅅㅇ) (눈_눈) (ᵔᴥᵔ) (ﾉ◕ヮ◕)ﾉ (ノಠ益ಠ)ノ";
+    let bodies = parse_memes(faces, &ParseOptions::default());
+    println!("{:#?}", bodies);
+    assert_eq!(8, bodies.memes.len());
+}
+
+#[test]
+fn test_technical(){
+    let cases = [
+        ("foo::bar", Some(Technical::Identifier)),
+        ("foo_bar", Some(Technical::Identifier)),
+        ("src/lib.rs", Some(Technical::Identifier)),
+        ("self.x", Some(Technical::Identifier)),
+        ("https://x.y/ä", Some(Technical::Url)),
+        ("ftp://example.com", Some(Technical::Url)),
+        ("www.example.com", Some(Technical::Url)),
+        ("me@example.com", Some(Technical::Email)),
+        ("ĳ.müller@beispiel.de", Some(Technical::Email)),
+        ("α+β", Some(Technical::Math)),
+        ("λx.x", Some(Technical::Math)),
+        ("2π", Some(Technical::Math)),
+        ("θ ≤ π/2", Some(Technical::Math)),
+        ("\\frac{a}{b}", Some(Technical::Latex)),
+        ("\\alpha", Some(Technical::Latex)),
+        ("x^{2}", Some(Technical::Latex)),
+        ("$e^x$", Some(Technical::Latex)),
+        ("$1+1$", Some(Technical::Latex)),
+        ("_", Some(Technical::Identifier)),
+        ("_, b", Some(Technical::Identifier)),
+        ("x, _", Some(Technical::Identifier)),
+        ("$_$", None),
+        ("x_x", None),
+        ("o.O", None),
+        ("UwU", None),
+        ("-_-", None),
+        ("^ω^", None),
+        ("ΘεΘ", None),
+        ("\\/", None),
+        ("\\o/", None),
+        ("°□°", None),
+        (" ͡° ͜ʖ ͡°", None),
+        ("@_@", None),
+    ];
+    for &(face, technical) in cases.iter(){
+        println!("{} {:?}", face, recognize_technical(face));
+        assert_eq!(technical, recognize_technical(face), "{}", face);
+        if technical.is_some(){
+            assert!(!is_meme(face), "{}", face);
+        }
+    }
 }
 
+#[test]
+fn test_meme_technical(){
+    let meme = r#"Call (foo::bar) see (https://x.y/ä) or (me@example.com) for (α+β) and (λx.x) in (\frac{a}{b}) ( -_- ) (^ω^)"#;
+    println!("{}", meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
+    println!("{:#?}",bodies);
+    assert_eq!(2, bodies.memes.len());
+    assert_eq!(" -_- ", bodies.memes[0].head.face);
+    assert_eq!("^ω^", bodies.memes[1].head.face);
+    let bodies = parse_memes("money ($_$) and ($x$)", &ParseOptions::default());
+    assert_eq!(1, bodies.memes.len());
+    assert_eq!("$_$", bodies.memes[0].head.face);
+}
 
+#[test]
+fn test_meme_code_span(){
+    let meme = r#"Use `(╯°□°）╯︵ ┻━┻` or `ಠ_ಠ` in code, but not ಠ_ಠ (ツ)"#;
+    println!("{}", meme);
+    let bodies = parse_memes(meme, &ParseOptions::default());
+    println!("{:#?}",bodies);
+    assert_eq!(2, bodies.memes.len());
+    assert_eq!("ಠ_ಠ", bodies.memes[0].head.face);
+    assert_eq!("ツ", bodies.memes[1].head.face);
+    // the placeholders in the patterns of the code are not faces
+    let cases = [
+        ("match x { Some(_) => 1, Err(_) => 2 }", vec![]),
+        ("let (_, b) = t;", vec![]),
+        ("(x, _) => (^_^)", vec!["^_^"]),
+    ];
+    for &(code, ref expected) in cases.iter(){
+        let faces: Vec<String> = parse_memes(code, &ParseOptions::default()).memes.into_iter().map(|m| m.head.face).collect();
+        assert_eq!(expected, &faces, "{}", code);
+    }
+}
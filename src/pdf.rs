@@ -0,0 +1,658 @@
+//! Lays out the memes onto the pages of a pdf, for printing meme sheets.
+//!
+//! The drawing is the same typed element tree that is written out as svg,
+//! the lines are kept whole and a page is broken in between the lines.
+//! The text is shaped and drawn with subsets of the bundled fonts embedded in the pdf.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+use rustybuzz::ttf_parser::{name_id, GlyphId};
+use rustybuzz::{Face, UnicodeBuffer};
+use svgtypes::Paint;
+use unicode_segmentation::UnicodeSegmentation;
+
+use {parse_memes, layout, line_bounds, Body, Bounds, Content as Node, Element, RenderOptions, FALLBACK_FONTS};
+#[cfg(test)]
+use Theme;
+
+/// points in 1 pixel, at 96 pixels per inch
+const PX_TO_PT: f32 = 0.75;
+
+/// points in 1 millimeter
+const MM_TO_PT: f32 = 72.0 / 25.4;
+
+/// The size of the paper
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum PaperSize {
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Legal,
+    /// the width and height in millimeters
+    Custom(f32, f32),
+}
+
+impl PaperSize {
+
+    /// the width and height in millimeters
+    fn size(&self) -> (f32, f32) {
+        match *self{
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::A5 => (148.0, 210.0),
+            PaperSize::Letter => (215.9, 279.4),
+            PaperSize::Legal => (215.9, 355.6),
+            PaperSize::Custom(width, height) => (width, height),
+        }
+    }
+}
+
+/// Options on how the drawing is laid out onto the pages,
+/// built by chaining the setters starting from `PdfOptions::new()`
+#[derive(Clone,Debug,PartialEq)]
+pub struct PdfOptions {
+    paper_size: PaperSize,
+    landscape: bool,
+    /// the top, right, bottom and left margin in millimeters
+    margins: (f32, f32, f32, f32),
+}
+
+impl PdfOptions {
+
+    pub fn new() -> PdfOptions {
+        PdfOptions::default()
+    }
+
+    pub fn paper_size(mut self, paper_size: PaperSize) -> PdfOptions {
+        self.paper_size = paper_size;
+        self
+    }
+
+    /// swap the width and height of the paper
+    pub fn landscape(mut self, landscape: bool) -> PdfOptions {
+        self.landscape = landscape;
+        self
+    }
+
+    /// the top, right, bottom and left margin in millimeters
+    pub fn margins(mut self, top: f32, right: f32, bottom: f32, left: f32) -> PdfOptions {
+        self.margins = (top, right, bottom, left);
+        self
+    }
+
+    /// the width and height of the page in points
+    fn page_size(&self) -> (f32, f32) {
+        let (width, height) = self.paper_size.size();
+        let (width, height) = if self.landscape{ (height, width) } else { (width, height) };
+        (width * MM_TO_PT, height * MM_TO_PT)
+    }
+}
+
+impl Default for PdfOptions {
+    fn default() -> PdfOptions {
+        PdfOptions{
+            paper_size: PaperSize::default(),
+            landscape: false,
+            margins: (15.0, 15.0, 15.0, 15.0),
+        }
+    }
+}
+
+/// return the memes of the text laid out onto the pages of a pdf.
+/// The drawing is scaled down to fit the width of the page and long inputs are continued on the next page,
+/// the background of the theme is printed over the whole page
+pub fn to_pdf(s: &str, options: &RenderOptions, pdf_options: &PdfOptions) -> Vec<u8> {
+    let mut fonts = Fonts::new();
+    let contents = draw_pages(s, options, pdf_options, &mut fonts);
+    write_pdf(&contents, &fonts, pdf_options.page_size())
+}
+
+/// the contents of each page, the glyphs that are drawn are recorded in the fonts
+fn draw_pages(s: &str, options: &RenderOptions, pdf_options: &PdfOptions, fonts: &mut Fonts) -> Vec<Vec<u8>> {
+    let lines: Vec<&str> = s.lines().collect();
+    let bodies: Vec<Body> = lines.iter().map(|line| parse_memes(line, &options.parse)).collect();
+    let rows = layout(&lines, &bodies, options);
+    let bounds: Vec<Bounds> = lines.iter().zip(&rows)
+        .map(|(line, row)| line_bounds(line, row, options))
+        .collect();
+
+    let (page_width, page_height) = pdf_options.page_size();
+    let (top, right, bottom, left) = pdf_options.margins;
+    let (top, right, bottom, left) = (top * MM_TO_PT, right * MM_TO_PT, bottom * MM_TO_PT, left * MM_TO_PT);
+    let drawing_left = bounds.iter().fold(0.0f32, |l, b| l.min(b.left));
+    let drawing_right = bounds.iter().fold(0.0f32, |r, b| r.max(b.right));
+    let fit = (page_width - left - right) / ((drawing_right - drawing_left) * PX_TO_PT);
+    let scale = PX_TO_PT * fit.min(1.0);
+
+    let (offsetx, offsety) = options.offset();
+    let background = options.theme.background.as_ref().and_then(|color| parse_color(color, "white"));
+    let mut contents = vec![];
+    for page in paginate(&bounds, (page_height - top - bottom) / scale){
+        let page_top = bounds[page.clone()].iter().fold(f32::MAX, |t, b| t.min(b.top));
+        let mut content = Content::new();
+        if let Some((r, g, b)) = background{
+            content.set_fill_rgb(r, g, b);
+            content.rect(0.0, 0.0, page_width, page_height);
+            content.fill_nonzero();
+        }
+        let mut painter = Painter{
+            content,
+            fonts: &mut *fonts,
+            options,
+            scale,
+            origin: (left - (drawing_left + offsetx) * scale, page_height - top + (page_top + offsety) * scale),
+        };
+        for (body, row) in bodies[page.clone()].iter().zip(&rows[page]){
+            for element in body.get_line_svg_elements(row, options){
+                painter.draw(&element);
+            }
+        }
+        contents.push(painter.content.finish());
+    }
+    contents
+}
+
+/// the lines on each page, a page is broken before the line that doesn't fit in the height.
+/// A line that is taller than the page is on a page of its own
+fn paginate(bounds: &[Bounds], height: f32) -> Vec<Range<usize>> {
+    let mut pages = vec![];
+    let mut start = 0;
+    let mut top = f32::MAX;
+    let mut bottom = f32::MIN;
+    for (i, b) in bounds.iter().enumerate(){
+        if i > start && b.bottom.max(bottom) - b.top.min(top) > height{
+            pages.push(start..i);
+            start = i;
+            top = f32::MAX;
+            bottom = f32::MIN;
+        }
+        top = top.min(b.top);
+        bottom = bottom.max(b.bottom);
+    }
+    pages.push(start..bounds.len());
+    pages
+}
+
+/// Draws the elements of the drawing onto the content of a page,
+/// the pixels of the drawing are scaled into points with the y axis pointing up
+struct Painter<'a> {
+    content: Content,
+    fonts: &'a mut Fonts,
+    options: &'a RenderOptions,
+    /// points per pixel
+    scale: f32,
+    /// the location of the origin of the drawing on the page
+    origin: (f32, f32),
+}
+
+impl<'a> Painter<'a> {
+
+    fn x(&self, x: f32) -> f32 {
+        self.origin.0 + x * self.scale
+    }
+
+    fn y(&self, y: f32) -> f32 {
+        self.origin.1 - y * self.scale
+    }
+
+    fn draw(&mut self, element: &Element) {
+        match element.name.as_str(){
+            "g" => {
+                for child in &element.children{
+                    if let Node::Element(ref child) = *child{
+                        self.draw(child);
+                    }
+                }
+            }
+            "text" => self.draw_text(element),
+            "circle" | "ellipse" | "rect" => self.draw_outline(element),
+            _ => (),
+        }
+    }
+
+    /// the outline of a head, filled and stroked with its inline style or the theme
+    fn draw_outline(&mut self, element: &Element) {
+        let number = |name| element.attribute(name).and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+        let theme = &self.options.theme;
+        let style = element.attribute("style").unwrap_or("");
+        let fill = style_property(style, "fill").unwrap_or(&theme.head_fill);
+        let stroke = style_property(style, "stroke").unwrap_or(&theme.stroke);
+        let fill = parse_color(fill, &theme.head_fill);
+        let stroke = parse_color(stroke, &theme.stroke);
+        if fill.is_none() && stroke.is_none(){
+            return;
+        }
+        if let Some((r, g, b)) = fill{
+            self.content.set_fill_rgb(r, g, b);
+        }
+        if let Some((r, g, b)) = stroke{
+            self.content.set_stroke_rgb(r, g, b);
+            self.content.set_line_width(self.options.head_stroke_width * self.scale);
+        }
+        let s = self.scale;
+        match element.name.as_str(){
+            "circle" => {
+                let (cx, cy, r) = (self.x(number("cx")), self.y(number("cy")), number("r") * s);
+                ellipse_path(&mut self.content, cx, cy, r, r);
+            }
+            "ellipse" => {
+                let (cx, cy) = (self.x(number("cx")), self.y(number("cy")));
+                ellipse_path(&mut self.content, cx, cy, number("rx") * s, number("ry") * s);
+            }
+            _ => {
+                let (x, y) = (self.x(number("x")), self.y(number("y")));
+                let (width, height) = (number("width") * s, number("height") * s);
+                rounded_rect_path(&mut self.content, x, y - height, width, height, number("rx") * s);
+            }
+        }
+        match (fill, stroke){
+            (Some(_), Some(_)) => self.content.fill_nonzero_and_stroke(),
+            (Some(_), None) => self.content.fill_nonzero(),
+            _ => self.content.stroke(),
+        };
+    }
+
+    /// the runs of text and tspan in the element, in the color of its inline style or the theme.
+    /// The brackets of the heads take up space but are not drawn
+    fn draw_text(&mut self, element: &Element) {
+        let number = |name| element.attribute(name).and_then(|v| v.parse::<f32>().ok());
+        let size = self.options.font_size * self.scale;
+        let mut runs = vec![];
+        for child in &element.children{
+            match *child{
+                Node::Text(ref text) => runs.push((None, self.fonts.shape(text, size), true)),
+                Node::Element(ref tspan) => {
+                    let text: String = tspan.children.iter()
+                        .filter_map(|c| match *c{ Node::Text(ref t) => Some(t.as_str()), _ => None })
+                        .collect();
+                    let x = tspan.attribute("x").and_then(|v| v.parse::<f32>().ok());
+                    runs.push((x, self.fonts.shape(&text, size), tspan.attribute("class") != Some("head")));
+                }
+            }
+        }
+        let width: f32 = runs.iter().flat_map(|(_, glyphs, _)| glyphs).map(|g| g.advance).sum();
+        if width == 0.0{
+            return;
+        }
+        let stretch = number("textLength").map_or(1.0, |length| length * self.scale / width);
+        let x = self.x(number("x").unwrap_or(0.0));
+        let mut pen = match element.attribute("text-anchor"){
+            Some("middle") => x - width * stretch / 2.0,
+            Some("end") => x - width * stretch,
+            _ => x,
+        };
+        let y = self.y(number("y").unwrap_or(0.0));
+        let color = element.attribute("style").and_then(|style| style_property(style, "fill")).unwrap_or(&self.options.theme.text);
+        let (r, g, b) = match parse_color(color, &self.options.theme.text){
+            Some(rgb) => rgb,
+            None => return,
+        };
+        self.content.begin_text();
+        self.content.set_fill_rgb(r, g, b);
+        let mut font = None;
+        for (run_x, glyphs, visible) in runs{
+            if let Some(run_x) = run_x{
+                pen = self.x(run_x);
+            }
+            for glyph in glyphs{
+                if visible{
+                    if font != Some(glyph.font){
+                        self.content.set_font(Name(format!("F{}", glyph.font).as_bytes()), size);
+                        font = Some(glyph.font);
+                    }
+                    self.content.set_text_matrix([stretch, 0.0, 0.0, 1.0, pen + glyph.dx * stretch, y + glyph.dy]);
+                    self.content.show(Str(&glyph.id.to_be_bytes()));
+                }
+                pen += glyph.advance * stretch;
+            }
+        }
+        self.content.end_text();
+    }
+}
+
+/// the value of a property in an inline style
+fn style_property<'a>(style: &'a str, name: &str) -> Option<&'a str> {
+    style.split(';')
+        .filter_map(|declaration| {
+            let mut parts = declaration.splitn(2, ':');
+            match (parts.next(), parts.next()){
+                (Some(property), Some(value)) if property.trim() == name => Some(value.trim()),
+                _ => None,
+            }
+        })
+        .next_back()
+}
+
+/// the red, green and blue of a css paint, none for no paint.
+/// The colors that can't be parsed and `currentColor` are the color of the theme,
+/// the alpha is not printed but a fully transparent color is no paint
+fn parse_color(color: &str, theme: &str) -> Option<(f32, f32, f32)> {
+    let channel = |v: u8| v as f32 / 255.0;
+    match Paint::from_str(color.trim()){
+        Ok(Paint::None) => None,
+        Ok(Paint::Color(c)) if c.alpha == 0 => None,
+        Ok(Paint::Color(c)) => Some((channel(c.red), channel(c.green), channel(c.blue))),
+        _ => parse_color(theme, "black"),
+    }
+}
+
+/// the distance of the control points from the ends of a quarter of an ellipse drawn with a bezier curve
+const KAPPA: f32 = 0.552_284_8;
+
+fn ellipse_path(content: &mut Content, cx: f32, cy: f32, rx: f32, ry: f32) {
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    content.move_to(cx + rx, cy);
+    content.cubic_to(cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry);
+    content.cubic_to(cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy);
+    content.cubic_to(cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry);
+    content.cubic_to(cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy);
+    content.close_path();
+}
+
+/// the rectangle from its bottom left corner, with the corners rounded
+fn rounded_rect_path(content: &mut Content, x: f32, y: f32, width: f32, height: f32, radius: f32) {
+    let r = radius.min(width / 2.0).min(height / 2.0);
+    let k = r * (1.0 - KAPPA);
+    content.move_to(x + r, y);
+    content.line_to(x + width - r, y);
+    content.cubic_to(x + width - k, y, x + width, y + k, x + width, y + r);
+    content.line_to(x + width, y + height - r);
+    content.cubic_to(x + width, y + height - k, x + width - k, y + height, x + width - r, y + height);
+    content.line_to(x + r, y + height);
+    content.cubic_to(x + k, y + height, x, y + height - k, x, y + height - r);
+    content.line_to(x, y + r);
+    content.cubic_to(x, y + k, x + k, y, x + r, y);
+    content.close_path();
+}
+
+/// A glyph shaped in one of the fonts, its advance and offsets are in points
+struct ShapedGlyph {
+    font: usize,
+    id: u16,
+    advance: f32,
+    dx: f32,
+    dy: f32,
+}
+
+/// The bundled fonts and the glyphs used from each of them,
+/// together with the text of each glyph so the text can be copied out of the pdf
+struct Fonts {
+    faces: Vec<Face<'static>>,
+    used: Vec<BTreeMap<u16, String>>,
+}
+
+impl Fonts {
+
+    fn new() -> Fonts {
+        let faces: Vec<Face<'static>> = FALLBACK_FONTS.iter()
+            .map(|data| Face::from_slice(data, 0).expect("the bundled font is valid"))
+            .collect();
+        let used = faces.iter().map(|_| BTreeMap::new()).collect();
+        Fonts{faces, used}
+    }
+
+    /// the first font that has the glyphs of the grapheme,
+    /// the joiners and variation selectors don't need to have a glyph.
+    /// A grapheme that is not whole in any of the fonts is split into its chars,
+    /// each found in the first font that has it
+    fn select<'g>(&self, grapheme: &'g str) -> Vec<(usize, &'g str)> {
+        let is_invisible = |c: char| c == '\u{200D}' || ('\u{FE00}'..='\u{FE0F}').contains(&c);
+        let has = |face: &Face, c: char| face.glyph_index(c).is_some();
+        if let Some(font) = self.faces.iter().position(|face| grapheme.chars().all(|c| is_invisible(c) || has(face, c))){
+            return vec![(font, grapheme)];
+        }
+        let mut pieces: Vec<(usize, Range<usize>)> = vec![];
+        for (i, c) in grapheme.char_indices(){
+            let font = self.faces.iter().position(|face| !is_invisible(c) && has(face, c));
+            match pieces.last_mut(){
+                Some(&mut (f, ref mut piece)) if font.is_none_or(|font| font == f) => piece.end = i + c.len_utf8(),
+                _ => pieces.push((font.unwrap_or(0), i..i + c.len_utf8())),
+            }
+        }
+        pieces.into_iter().map(|(font, piece)| (font, &grapheme[piece])).collect()
+    }
+
+    /// the glyphs of the text at the font size in points,
+    /// each run of graphemes found in the same font is shaped together
+    fn shape(&mut self, text: &str, size: f32) -> Vec<ShapedGlyph> {
+        let mut runs: Vec<(usize, String)> = vec![];
+        for grapheme in text.graphemes(true){
+            for (font, piece) in self.select(grapheme){
+                match runs.last_mut(){
+                    Some(&mut (f, ref mut run)) if f == font => run.push_str(piece),
+                    _ => runs.push((font, piece.to_string())),
+                }
+            }
+        }
+        let mut glyphs = vec![];
+        for (font, run) in runs{
+            let mut buffer = UnicodeBuffer::new();
+            buffer.push_str(&run);
+            buffer.guess_segment_properties();
+            let face = &self.faces[font];
+            let shaped = rustybuzz::shape(face, &[], buffer);
+            let units = size / face.units_per_em() as f32;
+            let clusters: Vec<usize> = shaped.glyph_infos().iter().map(|info| info.cluster as usize).collect();
+            for (info, position) in shaped.glyph_infos().iter().zip(shaped.glyph_positions()){
+                let start = info.cluster as usize;
+                let end = clusters.iter().cloned().filter(|&c| c > start).min().unwrap_or(run.len());
+                let id = info.glyph_id as u16;
+                // the missing glyphs share the notdef glyph, which has no text of its own
+                let text = if id == 0{ "" } else { &run[start..end] };
+                self.used[font].entry(id).or_insert_with(|| text.to_string());
+                glyphs.push(ShapedGlyph{
+                    font,
+                    id,
+                    advance: position.x_advance as f32 * units,
+                    dx: position.x_offset as f32 * units,
+                    dy: position.y_offset as f32 * units,
+                });
+            }
+        }
+        glyphs
+    }
+}
+
+/// the pdf with the contents of each page and the subsets of the fonts that are used
+fn write_pdf(contents: &[Vec<u8>], fonts: &Fonts, (width, height): (f32, f32)) -> Vec<u8> {
+    let mut pdf = Pdf::new();
+    let mut next = Ref::new(1);
+    let catalog_id = next.bump();
+    let tree_id = next.bump();
+    let page_ids: Vec<(Ref, Ref)> = contents.iter().map(|_| (next.bump(), next.bump())).collect();
+    let font_ids: Vec<(usize, Ref)> = fonts.used.iter().enumerate()
+        .filter(|&(_, used)| !used.is_empty())
+        .map(|(font, _)| (font, next.bump()))
+        .collect();
+
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.pages(tree_id)
+        .kids(page_ids.iter().map(|&(page_id, _)| page_id))
+        .count(page_ids.len() as i32);
+    for (&(page_id, content_id), content) in page_ids.iter().zip(contents){
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, width, height))
+            .parent(tree_id)
+            .contents(content_id);
+        let mut resources = page.resources();
+        let mut font_resources = resources.fonts();
+        for &(font, font_id) in &font_ids{
+            font_resources.pair(Name(format!("F{}", font).as_bytes()), font_id);
+        }
+        font_resources.finish();
+        resources.finish();
+        page.finish();
+        pdf.stream(content_id, &compress_to_vec_zlib(content, 6))
+            .filter(Filter::FlateDecode);
+    }
+    for &(font, font_id) in &font_ids{
+        let cid_id = next.bump();
+        let descriptor_id = next.bump();
+        let file_id = next.bump();
+        let cmap_id = next.bump();
+        write_font(&mut pdf, &fonts.faces[font], FALLBACK_FONTS[font], &fonts.used[font], font, [font_id, cid_id, descriptor_id, file_id, cmap_id]);
+    }
+    pdf.finish()
+}
+
+/// the font as a composite font with the glyph ids as the character codes,
+/// the font file is reduced to the used glyphs
+fn write_font(pdf: &mut Pdf, face: &Face, data: &[u8], used: &BTreeMap<u16, String>, font: usize, ids: [Ref; 5]) {
+    let [font_id, cid_id, descriptor_id, file_id, cmap_id] = ids;
+    let system_info = SystemInfo{registry: Str(b"Adobe"), ordering: Str(b"Identity"), supplement: 0};
+    let units = 1000.0 / face.units_per_em() as f32;
+    let postscript_name = face.names().into_iter()
+        .filter(|name| name.name_id == name_id::POST_SCRIPT_NAME)
+        .find_map(|name| name.to_string())
+        .unwrap_or_else(|| format!("Font{}", font));
+    // the subset is tagged with 6 upper case letters that are unique to the font
+    let base_font = format!("MEMEN{}+{}", (b'A' + font as u8) as char, postscript_name);
+    let base_font = Name(base_font.as_bytes());
+
+    pdf.type0_font(font_id)
+        .base_font(base_font)
+        .encoding_predefined(Name(b"Identity-H"))
+        .descendant_font(cid_id)
+        .to_unicode(cmap_id);
+
+    let mut cid = pdf.cid_font(cid_id);
+    cid.subtype(CidFontType::Type2)
+        .base_font(base_font)
+        .system_info(system_info)
+        .font_descriptor(descriptor_id)
+        .default_width(0.0)
+        .cid_to_gid_map_predefined(Name(b"Identity"));
+    let mut widths = cid.widths();
+    for &id in used.keys(){
+        let advance = face.glyph_hor_advance(GlyphId(id)).unwrap_or(0);
+        widths.consecutive(id, [advance as f32 * units]);
+    }
+    widths.finish();
+    cid.finish();
+
+    let bbox = face.global_bounding_box();
+    pdf.font_descriptor(descriptor_id)
+        .name(base_font)
+        .flags(FontFlags::SYMBOLIC)
+        .bbox(Rect::new(bbox.x_min as f32 * units, bbox.y_min as f32 * units, bbox.x_max as f32 * units, bbox.y_max as f32 * units))
+        .italic_angle(0.0)
+        .ascent(face.ascender() as f32 * units)
+        .descent(face.descender() as f32 * units)
+        .cap_height(face.capital_height().unwrap_or(face.ascender()) as f32 * units)
+        .stem_v(80.0)
+        .font_file2(file_id);
+
+    let mut glyphs: Vec<u16> = used.keys().cloned().collect();
+    glyphs.push(0);
+    let subset = subsetter::subset(data, 0, subsetter::Profile::pdf(&glyphs)).expect("the bundled font can be subset");
+    pdf.stream(file_id, &compress_to_vec_zlib(&subset, 6))
+        .filter(Filter::FlateDecode)
+        .pair(Name(b"Length1"), subset.len() as i32);
+
+    let mut cmap = UnicodeCmap::new(Name(b"Custom"), system_info);
+    for (&id, text) in used{
+        if !text.is_empty(){
+            cmap.pair_with_multiple(id, text.chars());
+        }
+    }
+    pdf.stream(cmap_id, &cmap.finish());
+}
+
+#[test]
+fn test_paginate(){
+    let line = |top: f32, bottom: f32| Bounds{left: 0.0, top, right: 80.0, bottom};
+    let bounds = [line(0.0, 16.0), line(16.0, 32.0), line(32.0, 48.0), line(48.0, 64.0), line(64.0, 80.0)];
+    assert_eq!(vec![0..2, 2..4, 4..5], paginate(&bounds, 40.0));
+    let tall = [line(0.0, 16.0), line(-8.0, 100.0), line(32.0, 48.0)];
+    assert_eq!(vec![0..1, 1..2, 2..3], paginate(&tall, 40.0));
+    assert_eq!(vec![0..0], paginate(&[], 40.0));
+}
+
+#[test]
+fn test_parse_color(){
+    assert_eq!(Some((1.0, 0.0, 0.0)), parse_color("#f00", "black"));
+    assert_eq!(Some((1.0, 1.0, 1.0)), parse_color("#ffffff", "black"));
+    assert_eq!(Some((0.0, 0.0, 1.0)), parse_color("blue", "black"));
+    assert_eq!(Some((1.0, 0.0, 1.0)), parse_color("fuchsia", "black"));
+    assert_eq!(Some((1.0, 0.0, 0.0)), parse_color("rgb(255, 0, 0)", "black"));
+    assert_eq!(Some((0.0, 1.0, 0.0)), parse_color("#00ff0080", "black"));
+    assert_eq!(None, parse_color("none", "black"));
+    assert_eq!(None, parse_color("transparent", "black"));
+    assert_eq!(None, parse_color("rgba(255, 0, 0, 0)", "black"));
+    // the colors that are not known are the color of the theme
+    assert_eq!(Some((1.0, 1.0, 1.0)), parse_color("currentColor", "white"));
+    assert_eq!(Some((1.0, 1.0, 1.0)), parse_color("bogus", "white"));
+    assert_eq!(Some((0.0, 0.0, 0.0)), parse_color("bogus", "bogus"));
+    assert_eq!(Some("red"), style_property("fill: #ffd5d5;stroke: red;", "stroke"));
+    assert_eq!(None, style_property("fill: #ffd5d5;", "stroke"));
+}
+
+#[test]
+fn test_pdf(){
+    let text = "Look of disapproval ಠ_ಠ\n( ͡° ͜ʖ ͡°) 🚀\n".repeat(40);
+    let pdf = to_pdf(&text, &RenderOptions::new().rest_text(true), &PdfOptions::new().paper_size(PaperSize::A5));
+    let raw = String::from_utf8_lossy(&pdf);
+    assert!(raw.starts_with("%PDF-"));
+    let pages = raw.matches("/Type /Page\n").count();
+    assert!(pages > 1, "{} pages", pages);
+    // the fonts are embedded as subsets, which are smaller than the bundled fonts
+    assert_eq!(3, raw.matches("/FontFile2").count());
+    assert!(raw.contains("/BaseFont /MEMENA+DejaVuSans"));
+    assert!(raw.contains("/BaseFont /MEMENB+NotoSansKannada"));
+    assert!(raw.contains("/BaseFont /MEMENC+NotoEmoji"));
+    assert!(pdf.len() < FALLBACK_FONTS.iter().map(|font| font.len()).sum::<usize>() / 4);
+
+    let landscape = to_pdf(&text, &RenderOptions::new(), &PdfOptions::new().paper_size(PaperSize::A5).landscape(true));
+    let landscape_pages = String::from_utf8_lossy(&landscape).matches("/Type /Page\n").count();
+    assert!(landscape_pages > pages);
+}
+
+#[test]
+fn test_fallback_fonts(){
+    let fonts = Fonts::new();
+    let is_drawn = |c: char| c.is_control() || c.is_whitespace() || c == '\u{200D}' || ('\u{FE00}'..='\u{FE0F}').contains(&c)
+        || fonts.faces.iter().any(|face| face.glyph_index(c).is_some());
+    let examples = [
+        include_str!("../examples/meme.mem"),
+        include_str!("../examples/donger.mem"),
+        include_str!("../examples/reddong.mem"),
+        include_str!("../examples/emoji.mem"),
+        include_str!("../examples/sample.mem"),
+    ];
+    for text in examples.iter(){
+        let missing: String = text.chars().filter(|&c| !is_drawn(c)).collect();
+        assert_eq!("", missing);
+    }
+}
+
+#[test]
+fn test_no_missing_glyphs(){
+    for text in [include_str!("../examples/meme.mem"), include_str!("../examples/emoji.mem")].iter(){
+        let mut fonts = Fonts::new();
+        draw_pages(text, &RenderOptions::new(), &PdfOptions::new(), &mut fonts);
+        // the glyph 0 is the box drawn for a char that is not in the font
+        assert!(fonts.used.iter().all(|used| !used.contains_key(&0)));
+    }
+    let fonts = Fonts::new();
+    // the skin tone is taken from another font than the emoji it modifies
+    assert_eq!(vec![(2, "💪"), (3, "🏾")], fonts.select("💪🏾"));
+    assert_eq!(vec![(1, "ಠ")], fonts.select("ಠ"));
+}
+
+#[test]
+fn test_pdf_background(){
+    let draw = |theme: Theme| {
+        let mut fonts = Fonts::new();
+        let contents = draw_pages("(ツ)", &RenderOptions::new().theme(theme), &PdfOptions::new(), &mut fonts);
+        String::from_utf8_lossy(&contents[0]).to_string()
+    };
+    let dark = draw(Theme::dark());
+    assert!(dark.starts_with("0.11764706 0.11764706 0.11764706 rg\n0 0 595.27563 841.8898 re\nf\n"), "{}", dark);
+    assert!(dark.contains("0.9019608 0.9019608 0.9019608 rg"));
+    let transparent = draw(Theme::transparent());
+    assert!(!transparent.contains(" re\n"));
+}
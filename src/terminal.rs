@@ -0,0 +1,245 @@
+//! Shows the memes inline in a terminal.
+//!
+//! Each meme is rasterized into an image as wide as its text and 1 line tall,
+//! and written out in its place through the graphics protocol of the terminal.
+//! The rest of the text is written out as it is.
+//! Terminals without graphics get the memes as colored text, with the face highlighted.
+
+use std::slice;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use icy_sixel::{sixel_string, DiffusionMethod, MethodForLargest, MethodForRep, PixelFormat, Quality};
+
+use {parse_memes, layout, rasterize, get_styles, get_background, text_width, Body, Bounds, Content, Element, Meme, RenderOptions};
+
+/// the size of the base64 chunks of an image sent through the kitty graphics protocol
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// The way the images are shown in the terminal
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum Graphics {
+    /// the kitty graphics protocol, also supported by WezTerm and Ghostty
+    Kitty,
+    /// sixel images, supported by foot, mlterm, contour and xterm started with sixel support
+    Sixel,
+    /// no images, the memes are colored with ansi escape codes
+    #[default]
+    Ansi,
+}
+
+impl Graphics {
+
+    /// guess the graphics of the terminal from the environment variables set by the terminal
+    pub fn detect() -> Graphics {
+        Graphics::detect_from(|name| ::std::env::var(name).ok())
+    }
+
+    fn detect_from<F: Fn(&str) -> Option<String>>(var: F) -> Graphics {
+        let term = var("TERM").unwrap_or_default();
+        let term_program = var("TERM_PROGRAM").unwrap_or_default();
+        if var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || term == "xterm-ghostty"
+            || term_program == "WezTerm" || term_program == "ghostty"{
+            Graphics::Kitty
+        }else if term.contains("sixel") || ["foot", "mlterm", "contour", "yaft"].iter().any(|t| term.starts_with(t)){
+            Graphics::Sixel
+        }else{
+            Graphics::Ansi
+        }
+    }
+}
+
+/// Options on how the memes are shown in the terminal,
+/// built by chaining the setters starting from `TerminalOptions::new()`
+#[derive(Clone,Debug,Default)]
+pub struct TerminalOptions {
+    graphics: Graphics,
+    render: RenderOptions,
+}
+
+impl TerminalOptions {
+
+    pub fn new() -> TerminalOptions {
+        TerminalOptions::default()
+    }
+
+    pub fn graphics(mut self, graphics: Graphics) -> TerminalOptions {
+        self.graphics = graphics;
+        self
+    }
+
+    /// the options on how the memes are drawn,
+    /// the cell size is the size in pixels of the images of sixel, which don't scale to the cells of the terminal
+    pub fn render_options(mut self, render: RenderOptions) -> TerminalOptions {
+        self.render = render;
+        self
+    }
+}
+
+/// return the text with the memes replaced by their images, ready to be written to the terminal
+pub fn to_terminal(s: &str, options: &TerminalOptions) -> String {
+    let mut out = String::new();
+    for line in s.lines(){
+        let body = parse_memes(line, &options.render.parse);
+        out.push_str(&line_to_terminal(line, &body, options));
+        out.push('\n');
+    }
+    out
+}
+
+/// return the line with the memes in the body, which is parsed from the line, replaced by their images.
+/// The body may be a line of a parsed document, its spans are taken from the start of the line.
+/// Only the memes are drawn, the rest of the line is left as it is
+pub fn line_to_terminal(line: &str, body: &Body, options: &TerminalOptions) -> String {
+    let mut out = String::new();
+    let mut position = 0;
+    for meme in &body.memes{
+        let span = meme.span.start - body.offset..meme.span.end - body.offset;
+        out.push_str(&line[position..span.start]);
+        match options.graphics{
+            Graphics::Ansi => out.push_str(&get_ansi_meme(line, meme, body.offset)),
+            Graphics::Kitty | Graphics::Sixel => {
+                let columns = text_width(&line[span.clone()]);
+                let image = get_meme_image(line, body, meme, columns, &options.render);
+                if options.graphics == Graphics::Kitty{
+                    out.push_str(&get_kitty_image(&image, columns));
+                }else{
+                    out.push_str(&get_sixel_image(&image, columns));
+                }
+            }
+        }
+        position = span.end;
+    }
+    out.push_str(&line[position..]);
+    out
+}
+
+/// the meme in cyan, with its face in bold yellow,
+/// the spans of the meme are moved back by the offset of the line
+fn get_ansi_meme(line: &str, meme: &Meme, offset: usize) -> String {
+    let face = &meme.head.face_span;
+    format!("\x1b[36m{}\x1b[1;33m{}\x1b[22;36m{}\x1b[0m",
+        &line[meme.span.start - offset..face.start - offset],
+        &line[face.start - offset..face.end - offset],
+        &line[face.end - offset..meme.span.end - offset])
+}
+
+/// the pixels of the meme scaled down to fit in the cells of its text,
+/// the head that is taller than the line is fitted into it
+fn get_meme_image(line: &str, body: &Body, meme: &Meme, columns: usize, options: &RenderOptions) -> ::resvg::tiny_skia::Pixmap {
+    let options = options.clone().padding(0.0, 0.0);
+    let rows = layout(&[line], slice::from_ref(body), &options);
+    let row = &rows[0];
+    let cells = Bounds{
+        left: meme.start_column() as f32 * options.text_width,
+        top: 0.0,
+        right: (meme.start_column() + columns) as f32 * options.text_width,
+        bottom: options.text_height,
+    };
+    let drawing = cells.union(&row.outline(&meme.head, &options).stroke_bounds(row.top, &options));
+    // the view box is widened or heightened around the drawing to the shape of the cells
+    let (width, height) = (cells.right - cells.left, cells.bottom - cells.top);
+    let scale = f32::max((drawing.right - drawing.left) / width, (drawing.bottom - drawing.top) / height);
+    let (center_x, center_y) = ((drawing.left + drawing.right) / 2.0, (drawing.top + drawing.bottom) / 2.0);
+    let view_box = Bounds{
+        left: center_x - width * scale / 2.0,
+        top: center_y - height * scale / 2.0,
+        right: center_x + width * scale / 2.0,
+        bottom: center_y + height * scale / 2.0,
+    };
+
+    let mut svg = Element::new("svg")
+        .set("xmlns", "http://www.w3.org/2000/svg")
+        .set("font-size", options.font_size)
+        .set("font-family", options.font_family.as_str())
+        .set("width", width)
+        .set("height", height)
+        .set("viewBox", format!("{} {} {} {}", view_box.left, view_box.top, width * scale, height * scale))
+        .child(Content::Element(get_styles(&options)));
+    if let Some(background) = get_background(&view_box, &options){
+        svg = svg.child(Content::Element(background));
+    }
+    rasterize(&svg.child(Content::Element(meme.get_svg_group(row, &options))))
+}
+
+/// the image sent as a png through the kitty graphics protocol, stretched over the cells of the meme.
+/// The cursor is kept at the image and then moved past it, so the rest of the line follows the meme
+fn get_kitty_image(image: &::resvg::tiny_skia::Pixmap, columns: usize) -> String {
+    let png = image.encode_png().expect("the pixmap is encoded");
+    let data = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate(){
+        let more = if i + 1 < chunks.len(){ 1 } else { 0 };
+        let chunk = String::from_utf8_lossy(chunk);
+        if i == 0{
+            out.push_str(&format!("\x1b_Ga=T,f=100,q=2,C=1,c={},r=1,m={};{}\x1b\\", columns, more, chunk));
+        }else{
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    out.push_str(&format!("\x1b[{}C", columns));
+    out
+}
+
+/// the image as sixels, drawn from the saved cursor position which is then restored,
+/// since the terminals differ in where they leave the cursor after the image
+fn get_sixel_image(image: &::resvg::tiny_skia::Pixmap, columns: usize) -> String {
+    let sixels = sixel_string(image.data(), image.width() as i32, image.height() as i32,
+            PixelFormat::RGBA8888, DiffusionMethod::None, MethodForLargest::Auto, MethodForRep::Auto, Quality::HIGH)
+        .expect("the pixmap is encoded as sixels");
+    format!("\x1b7{}\x1b8\x1b[{}C", sixels, columns)
+}
+
+#[test]
+fn test_detect(){
+    let env = |vars: &'static [(&'static str, &'static str)]| move |name: &str| {
+        vars.iter().find(|&&(n, _)| n == name).map(|&(_, value)| value.to_string())
+    };
+    assert_eq!(Graphics::Kitty, Graphics::detect_from(env(&[("TERM", "xterm-kitty")])));
+    assert_eq!(Graphics::Kitty, Graphics::detect_from(env(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "WezTerm")])));
+    assert_eq!(Graphics::Sixel, Graphics::detect_from(env(&[("TERM", "foot")])));
+    assert_eq!(Graphics::Ansi, Graphics::detect_from(env(&[("TERM", "xterm-256color")])));
+    assert_eq!(Graphics::Ansi, Graphics::detect_from(env(&[])));
+}
+
+#[test]
+fn test_ansi(){
+    let text = "happy => ( ツ)\nno memes here\n";
+    let out = to_terminal(text, &TerminalOptions::new());
+    assert_eq!("happy => \x1b[36m(\x1b[1;33m ツ\x1b[22;36m)\x1b[0m\nno memes here\n", out);
+}
+
+#[test]
+fn test_kitty(){
+    let line = "happy => ( ツ) yes";
+    let body = parse_memes(line, &Default::default());
+    let out = line_to_terminal(line, &body, &TerminalOptions::new().graphics(Graphics::Kitty));
+    assert!(out.starts_with("happy => \x1b_Ga=T,f=100,q=2,C=1,c=6,r=1,m=0;"));
+    assert!(out.ends_with("\x1b\\\x1b[6Cyes"));
+    // the image is a png as big as the cells of the meme, including the space after it
+    let payload = &out[out.find(';').unwrap() + 1..out.find("\x1b\\").unwrap()];
+    let png = STANDARD.decode(payload).unwrap();
+    let image = ::resvg::tiny_skia::Pixmap::decode_png(&png).unwrap();
+    assert_eq!((48, 16), (image.width(), image.height()));
+}
+
+#[test]
+fn test_document_line(){
+    let text = "first line here\nhappy => ( ツ) yes";
+    let doc = ::parse(text);
+    let line = text.lines().nth(1).unwrap();
+    let ansi = line_to_terminal(line, &doc.lines[1], &TerminalOptions::new());
+    assert_eq!("happy => \x1b[36m(\x1b[1;33m ツ\x1b[22;36m) \x1b[0myes", ansi);
+    let kitty = line_to_terminal(line, &doc.lines[1], &TerminalOptions::new().graphics(Graphics::Kitty));
+    assert!(kitty.starts_with("happy => \x1b_Ga=T,f=100,q=2,C=1,c=6,r=1,m=0;"));
+    assert!(kitty.ends_with("\x1b\\\x1b[6Cyes"));
+}
+
+#[test]
+fn test_sixel(){
+    let text = "happy => ( ツ) yes\n";
+    let out = to_terminal(text, &TerminalOptions::new().graphics(Graphics::Sixel));
+    assert!(out.starts_with("happy => \x1b7\x1bP"));
+    assert!(out.ends_with("\x1b\\\x1b8\x1b[6Cyes\n"));
+}